@@ -0,0 +1,133 @@
+use clap::Parser;
+use sandhiya::mcs::{DEFAULT_OUTPUT_PATH, start_simulation};
+use sandhiya::message::{CancellationToken, SimulationMessage};
+use sandhiya::scenario::load_scenario;
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+    process::ExitCode,
+    sync::mpsc,
+    thread,
+};
+
+const PROGRESS_BAR_WIDTH: usize = 40;
+
+/// Runs a SANDHIYA simulation without the GUI.
+#[derive(Parser)]
+#[command(version)]
+struct Args {
+    /// Scenario file (TOML, or JSON if it ends in .json).
+    scenario: PathBuf,
+    /// Number of trials, overriding the scenario.
+    #[arg(long)]
+    trials: Option<usize>,
+    /// Number of months, overriding the scenario.
+    #[arg(long)]
+    months: Option<usize>,
+    /// Master seed, overriding the scenario.
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Monthly income floor, overriding the scenario.
+    #[arg(long)]
+    income_floor: Option<f64>,
+    /// Where to write the parquet output.
+    #[arg(long, default_value = DEFAULT_OUTPUT_PATH)]
+    output: PathBuf,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let mut config = match load_scenario(&args.scenario) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Some(trials) = args.trials {
+        config.trials = trials;
+    }
+    if let Some(months) = args.months {
+        config.num_months = months;
+    }
+    if let Some(seed) = args.seed {
+        config.seed = seed;
+    }
+    if let Some(income_floor) = args.income_floor {
+        config.income_floor = income_floor;
+    }
+    if let Err(e) = config.validate() {
+        eprintln!("error: {}", e);
+        return ExitCode::FAILURE;
+    }
+
+    let (sender, receiver) = mpsc::channel::<SimulationMessage>();
+    let worker = thread::spawn(move || {
+        start_simulation(
+            &config,
+            &args.output,
+            Some(sender),
+            &CancellationToken::default(),
+        )
+    });
+
+    let mut progress = 0.0;
+    for message in receiver {
+        if let SimulationMessage::Progress(value) = message {
+            // Trials finish out of order, so only ever move the bar forwards.
+            if value > progress {
+                progress = value;
+                print_progress(progress);
+            }
+        }
+    }
+    eprintln!();
+
+    match worker.join() {
+        Ok(Ok(summary)) => {
+            println!("{}", summary.output_path);
+            println!("{}", summary.summary_path);
+            if let Some(convergence) = &summary.convergence {
+                eprintln!("{}", convergence);
+            }
+            if let Some(variance_reduction) = &summary.variance_reduction {
+                eprintln!("{}", variance_reduction);
+            }
+            for metric in &summary.metrics {
+                let stats = &metric.per_trial;
+                eprintln!(
+                    "{}: mean {:.2}, P10 {:.2}, P50 {:.2}, P90 {:.2}, P(< {}) {:.1}%",
+                    metric.metric,
+                    stats.mean,
+                    stats.p10,
+                    stats.p50,
+                    stats.p90,
+                    summary.income_floor,
+                    stats.prob_below_floor * 100.0
+                );
+            }
+            ExitCode::SUCCESS
+        }
+        Ok(Err(e)) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+        Err(_) => {
+            eprintln!("error: the simulation thread panicked");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn print_progress(progress: f32) {
+    let filled = ((progress * PROGRESS_BAR_WIDTH as f32) as usize).min(PROGRESS_BAR_WIDTH);
+    let mut stderr = io::stderr();
+    let _ = write!(
+        stderr,
+        "\r[{}{}] {:>3.0}%",
+        "#".repeat(filled),
+        " ".repeat(PROGRESS_BAR_WIDTH - filled),
+        progress * 100.0
+    );
+    let _ = stderr.flush();
+}
@@ -0,0 +1,336 @@
+use eframe::egui::{self, Color32};
+use egui_plot::{Bar, BarChart, HLine, Legend, Line, Plot, PlotPoints, Polygon, VLine};
+use sandhiya::sensitivity::{Outcome, SENSITIVITY_METRIC, SensitivityAnalysis, Swing};
+use sandhiya::sobol::{SobolAnalysis, SobolIndex};
+use sandhiya::summary::{MetricSummary, SimulationSummary, Statistics};
+
+/// Number of bars in the histogram.
+const HISTOGRAM_BINS: usize = 50;
+
+/// Most points drawn on the empirical CDF; larger runs are thinned evenly.
+const MAX_CDF_POINTS: usize = 1000;
+
+const CHART_HEIGHT: f32 = 260.0;
+const DATA_HISTOGRAM_HEIGHT: f32 = 100.0;
+const DATA_HISTOGRAM_WIDTH: f32 = 300.0;
+
+/// Picks one percentile out of a month's statistics.
+type Percentile = fn(&Statistics) -> f64;
+
+const BAND_COLOR: Color32 = Color32::from_rgb(100, 150, 230);
+const FLOOR_COLOR: Color32 = Color32::from_rgb(220, 80, 80);
+const HIGH_COLOR: Color32 = Color32::from_rgb(90, 180, 120);
+
+/// Histogram, empirical CDF and monthly fan chart of a finished run.
+/// The plotted series are worked out once, when the run finishes, rather than every frame.
+pub struct ResultCharts {
+    income_floor: f64,
+    metrics: Vec<MetricCharts>,
+    selected: usize,
+    /// Set when the plots should fit their bounds to new data.
+    reset: bool,
+}
+
+struct MetricCharts {
+    name: String,
+    histogram: Vec<Bar>,
+    bin_width: f64,
+    cdf: Vec<[f64; 2]>,
+    per_month: Vec<(i64, Statistics)>,
+}
+
+impl ResultCharts {
+    pub fn new(summary: &SimulationSummary) -> Self {
+        Self {
+            income_floor: summary.income_floor,
+            metrics: summary.metrics.iter().map(MetricCharts::new).collect(),
+            selected: 0,
+            reset: true,
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        if self.metrics.is_empty() {
+            return;
+        }
+        ui.horizontal(|ui| {
+            ui.label("Chart");
+            egui::ComboBox::from_id_salt("chart_metric")
+                .selected_text(&self.metrics[self.selected].name)
+                .show_ui(ui, |ui| {
+                    for (i, metric) in self.metrics.iter().enumerate() {
+                        if ui
+                            .selectable_value(&mut self.selected, i, &metric.name)
+                            .changed()
+                        {
+                            self.reset = true;
+                        }
+                    }
+                });
+        });
+
+        let metric = &self.metrics[self.selected];
+        let floor = self.income_floor;
+        let reset = std::mem::take(&mut self.reset);
+        let plot = |id: &str| {
+            let plot = Plot::new(id)
+                .height(CHART_HEIGHT)
+                .legend(Legend::default())
+                .allow_scroll(false);
+            if reset { plot.reset() } else { plot }
+        };
+
+        ui.columns(3, |columns| {
+            columns[0].label("Distribution of the average month per trial");
+            plot("result_histogram")
+                .x_axis_label(&metric.name)
+                .y_axis_label("Trials")
+                .show(&mut columns[0], |plot_ui| {
+                    plot_ui.bar_chart(
+                        BarChart::new("Trials", metric.histogram.clone())
+                            .width(metric.bin_width)
+                            .color(BAND_COLOR),
+                    );
+                    plot_ui.vline(VLine::new("Income floor", floor).color(FLOOR_COLOR));
+                });
+
+            columns[1].label("Cumulative probability");
+            plot("result_cdf")
+                .x_axis_label(&metric.name)
+                .y_axis_label("P(X ≤ x)")
+                .include_y(0.0)
+                .include_y(1.0)
+                .show(&mut columns[1], |plot_ui| {
+                    plot_ui.line(
+                        Line::new("Empirical CDF", PlotPoints::new(metric.cdf.clone()))
+                            .color(BAND_COLOR),
+                    );
+                    plot_ui.vline(VLine::new("Income floor", floor).color(FLOOR_COLOR));
+                });
+
+            columns[2].label("Month by month across trials");
+            plot("result_fan")
+                .x_axis_label("Month")
+                .y_axis_label(&metric.name)
+                .show(&mut columns[2], |plot_ui| {
+                    let bands: [(&str, Percentile, Percentile, u8); 2] = [
+                        ("P5-P95", |s| s.p5, |s| s.p95, 50),
+                        ("P10-P90", |s| s.p10, |s| s.p90, 90),
+                    ];
+                    for (name, low, high, alpha) in bands {
+                        // One quadrilateral per pair of months, since filled polygons must be convex.
+                        for pair in metric.per_month.windows(2) {
+                            let [(m0, s0), (m1, s1)] = pair else {
+                                continue;
+                            };
+                            let (m0, m1) = (*m0 as f64, *m1 as f64);
+                            let band =
+                                vec![[m0, low(s0)], [m1, low(s1)], [m1, high(s1)], [m0, high(s0)]];
+                            plot_ui.polygon(
+                                Polygon::new(name, PlotPoints::new(band))
+                                    .fill_color(Color32::from_rgba_unmultiplied(
+                                        BAND_COLOR.r(),
+                                        BAND_COLOR.g(),
+                                        BAND_COLOR.b(),
+                                        alpha,
+                                    ))
+                                    .stroke(egui::Stroke::NONE),
+                            );
+                        }
+                    }
+                    let median = metric
+                        .per_month
+                        .iter()
+                        .map(|(month, stats)| [*month as f64, stats.p50])
+                        .collect::<Vec<_>>();
+                    plot_ui.line(
+                        Line::new("P50", PlotPoints::new(median))
+                            .color(BAND_COLOR)
+                            .width(2.0),
+                    );
+                    plot_ui.hline(HLine::new("Income floor", floor).color(FLOOR_COLOR));
+                });
+        });
+    }
+}
+
+/// A small histogram of loaded data, such as an Empirical distribution's column.
+pub fn show_data_histogram(ui: &mut egui::Ui, id: egui::Id, sorted_values: &[f64]) {
+    let (bars, bin_width) = histogram(sorted_values);
+    Plot::new(id)
+        .height(DATA_HISTOGRAM_HEIGHT)
+        .width(DATA_HISTOGRAM_WIDTH)
+        .allow_zoom(false)
+        .allow_drag(false)
+        .allow_scroll(false)
+        .allow_boxed_zoom(false)
+        .show(ui, |plot_ui| {
+            plot_ui.bar_chart(
+                BarChart::new("Values", bars)
+                    .width(bin_width)
+                    .color(BAND_COLOR),
+            );
+        });
+}
+
+/// Tornado charts of how far each input's low and high runs move the mean and P10 away from
+/// the base run, with the most influential input at the top.
+pub fn show_tornado(ui: &mut egui::Ui, analysis: &SensitivityAnalysis) {
+    ui.columns(2, |columns| {
+        tornado(&mut columns[0], analysis, "Mean", |outcome| outcome.mean);
+        tornado(&mut columns[1], analysis, "P10", |outcome| outcome.p10);
+    });
+}
+
+fn tornado(
+    ui: &mut egui::Ui,
+    analysis: &SensitivityAnalysis,
+    name: &str,
+    statistic: fn(&Outcome) -> f64,
+) {
+    let base = statistic(&analysis.base);
+    // Rows count up from the bottom, so the largest swing goes last.
+    let swings: Vec<&Swing> = analysis.swings.iter().rev().collect();
+    let bars = |outcome: fn(&Swing) -> Outcome| {
+        swings
+            .iter()
+            .enumerate()
+            .map(|(row, swing)| {
+                Bar::new(row as f64, statistic(&outcome(swing)) - base)
+                    .base_offset(base)
+                    .name(swing.input.to_string())
+            })
+            .collect::<Vec<_>>()
+    };
+    ui.label(format!("{} {}", name, SENSITIVITY_METRIC));
+    Plot::new(("tornado", name))
+        .height(CHART_HEIGHT)
+        .legend(Legend::default())
+        .allow_zoom(false)
+        .allow_drag(false)
+        .allow_scroll(false)
+        .allow_boxed_zoom(false)
+        .y_axis_formatter(|mark, _| {
+            let row = mark.value.round();
+            match swings.get(row as usize) {
+                Some(swing) if row >= 0.0 && (mark.value - row).abs() < 1e-6 => {
+                    swing.input.to_string()
+                }
+                _ => String::new(),
+            }
+        })
+        .show(ui, |plot_ui| {
+            plot_ui.bar_chart(
+                BarChart::new("Low input", bars(|swing| swing.low))
+                    .horizontal()
+                    .width(0.6)
+                    .color(FLOOR_COLOR),
+            );
+            plot_ui.bar_chart(
+                BarChart::new("High input", bars(|swing| swing.high))
+                    .horizontal()
+                    .width(0.6)
+                    .color(HIGH_COLOR),
+            );
+            plot_ui.vline(VLine::new("Base", base).color(BAND_COLOR));
+        });
+}
+
+/// Grouped bars of each input's first- and total-order Sobol index.
+pub fn show_sobol(ui: &mut egui::Ui, analysis: &SobolAnalysis) {
+    let bars = |index: fn(&SobolIndex) -> f64, offset: f64| {
+        analysis
+            .indices
+            .iter()
+            .enumerate()
+            .map(|(column, input)| {
+                Bar::new(column as f64 + offset, index(input)).name(input.input.to_string())
+            })
+            .collect::<Vec<_>>()
+    };
+    Plot::new("sobol_indices")
+        .height(CHART_HEIGHT)
+        .legend(Legend::default())
+        .allow_zoom(false)
+        .allow_drag(false)
+        .allow_scroll(false)
+        .allow_boxed_zoom(false)
+        .include_y(0.0)
+        .include_y(1.0)
+        .x_axis_formatter(|mark, _| {
+            let column = mark.value.round();
+            match analysis.indices.get(column as usize) {
+                Some(index) if column >= 0.0 && (mark.value - column).abs() < 1e-6 => {
+                    index.input.to_string()
+                }
+                _ => String::new(),
+            }
+        })
+        .show(ui, |plot_ui| {
+            plot_ui.bar_chart(
+                BarChart::new("First order", bars(|index| index.first_order, -0.2))
+                    .width(0.4)
+                    .color(BAND_COLOR),
+            );
+            plot_ui.bar_chart(
+                BarChart::new("Total order", bars(|index| index.total_order, 0.2))
+                    .width(0.4)
+                    .color(HIGH_COLOR),
+            );
+        });
+}
+
+impl MetricCharts {
+    fn new(summary: &MetricSummary) -> Self {
+        let values = &summary.trial_values;
+        let (histogram, bin_width) = histogram(values);
+        Self {
+            name: summary.metric.clone(),
+            histogram,
+            bin_width,
+            cdf: empirical_cdf(values),
+            per_month: summary.per_month.clone(),
+        }
+    }
+}
+
+/// Bins sorted `values` into [`HISTOGRAM_BINS`] equal-width bars.
+fn histogram(values: &[f64]) -> (Vec<Bar>, f64) {
+    let (Some(&min), Some(&max)) = (values.first(), values.last()) else {
+        return (Vec::new(), 1.0);
+    };
+    // A run where every trial came out the same still gets one visible bar.
+    let bin_width = if max > min {
+        (max - min) / HISTOGRAM_BINS as f64
+    } else {
+        1.0
+    };
+    let mut counts = [0usize; HISTOGRAM_BINS];
+    for value in values {
+        let bin = ((value - min) / bin_width) as usize;
+        counts[bin.min(HISTOGRAM_BINS - 1)] += 1;
+    }
+    let bars = counts
+        .iter()
+        .enumerate()
+        .filter(|(_, count)| **count > 0)
+        .map(|(bin, count)| Bar::new(min + (bin as f64 + 0.5) * bin_width, *count as f64))
+        .collect();
+    (bars, bin_width)
+}
+
+/// Step points of the empirical CDF of sorted `values`, thinned to at most [`MAX_CDF_POINTS`].
+fn empirical_cdf(values: &[f64]) -> Vec<[f64; 2]> {
+    let n = values.len();
+    let step = n.div_ceil(MAX_CDF_POINTS).max(1);
+    let mut points: Vec<[f64; 2]> = (0..n)
+        .step_by(step)
+        .map(|i| [values[i], (i + 1) as f64 / n as f64])
+        .collect();
+    if let Some(&last) = values.last()
+        && !(n - 1).is_multiple_of(step)
+    {
+        points.push([last, 1.0]);
+    }
+    points
+}
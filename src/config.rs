@@ -0,0 +1,175 @@
+use crate::convergence::ConvergenceTarget;
+use crate::correlation::{CorrelatedInput, CorrelationMatrix};
+use crate::distributions::{DistributionInputs, Distributions};
+use crate::sampling::SamplingStrategy;
+use serde::{Deserialize, Serialize};
+
+/// Every input of the Monte Carlo model.
+///
+/// The GUI builds one of these from its editors, but it is plain data so other tools can
+/// construct it directly and pass it to [`crate::mcs::start_simulation`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimulationConfig {
+    /// Price to retailer per unit, drawn for every visit.
+    #[serde(with = "parameter")]
+    pub prices: (Distributions, DistributionInputs),
+    /// Retailers visited per day, drawn once per trial. Continuous draws are rounded.
+    #[serde(with = "parameter")]
+    pub retailers_per_day: (Distributions, DistributionInputs),
+    /// Working days per month, drawn once per trial. Continuous draws are rounded.
+    #[serde(with = "parameter")]
+    pub workdays_per_month: (Distributions, DistributionInputs),
+    /// Units per sale, drawn for every visit. Continuous draws are rounded.
+    #[serde(with = "parameter")]
+    pub units: (Distributions, DistributionInputs),
+    /// Conversion rate in percent, drawn once per trial.
+    #[serde(with = "parameter")]
+    pub conversion_rate: (Distributions, DistributionInputs),
+    /// Commission rate in percent of sale value.
+    pub commission_rate: f64,
+    /// Bonus paid per unit on top of the commission.
+    pub transport_bonus: f64,
+    /// Monthly income below which a distributor is considered to be struggling.
+    pub income_floor: f64,
+    pub num_months: usize,
+    /// Number of trials, or the most that may be run when `convergence` is set.
+    pub trials: usize,
+    /// Stops the run early once the chosen estimate is precise enough.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub convergence: Option<ConvergenceTarget>,
+    /// Correlations between the stochastic inputs. The identity samples them independently.
+    pub correlation: CorrelationMatrix,
+    /// How the once-per-trial inputs are spread over the trials.
+    pub sampling: SamplingStrategy,
+    /// Pairs each trial with one that mirrors all of its draws.
+    pub antithetic: bool,
+    /// Sharpens the mean commission with each trial's expected sale value, whose mean is known.
+    /// Needs independent inputs.
+    pub control_variate: bool,
+    /// Master seed from which every trial's RNG stream is derived.
+    pub seed: u64,
+}
+
+impl Default for SimulationConfig {
+    /// Count inputs start out on count distributions, and every input on parameters that
+    /// pass validation, so a default config runs as it is.
+    fn default() -> Self {
+        SimulationConfig {
+            prices: (
+                Distributions::Pert,
+                DistributionInputs {
+                    pert_min: 18.0,
+                    pert_mode: 20.0,
+                    pert_max: 26.0,
+                    ..Default::default()
+                },
+            ),
+            retailers_per_day: (
+                Distributions::Poisson,
+                DistributionInputs {
+                    poisson_lambda: 25.0,
+                    ..Default::default()
+                },
+            ),
+            workdays_per_month: (
+                Distributions::DiscreteUniform,
+                DistributionInputs {
+                    discrete_min: 20,
+                    discrete_max: 26,
+                    ..Default::default()
+                },
+            ),
+            units: (
+                Distributions::NegativeBinomial,
+                DistributionInputs {
+                    negbinomial_r: 4.0,
+                    negbinomial_p: 0.25,
+                    ..Default::default()
+                },
+            ),
+            conversion_rate: (
+                Distributions::Normal,
+                DistributionInputs {
+                    normal_mean: 40.0,
+                    normal_std: 5.0,
+                    ..Default::default()
+                },
+            ),
+            commission_rate: 0.0,
+            transport_bonus: 0.0,
+            income_floor: 0.0,
+            num_months: 12,
+            trials: 1000,
+            convergence: None,
+            correlation: CorrelationMatrix::default(),
+            sampling: SamplingStrategy::default(),
+            antithetic: false,
+            control_variate: false,
+            seed: 0,
+        }
+    }
+}
+
+impl SimulationConfig {
+    /// The distribution of one of the stochastic inputs.
+    pub fn input(&self, input: CorrelatedInput) -> &(Distributions, DistributionInputs) {
+        match input {
+            CorrelatedInput::WorkdaysPerMonth => &self.workdays_per_month,
+            CorrelatedInput::RetailersPerDay => &self.retailers_per_day,
+            CorrelatedInput::ConversionRate => &self.conversion_rate,
+            CorrelatedInput::Price => &self.prices,
+            CorrelatedInput::Units => &self.units,
+        }
+    }
+}
+
+/// Writes a `(Distributions, DistributionInputs)` pair as a single table, e.g.
+/// `prices = { distribution = "Normal", normal_mean = 20.0, normal_std = 2.0, ... }`.
+mod parameter {
+    use crate::distributions::{DistributionInputs, Distributions};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize)]
+    struct ParameterRef<'a> {
+        distribution: &'a Distributions,
+        #[serde(flatten)]
+        inputs: &'a DistributionInputs,
+    }
+
+    #[derive(Deserialize)]
+    struct Parameter {
+        #[serde(default)]
+        distribution: Distributions,
+        #[serde(flatten)]
+        inputs: DistributionInputs,
+    }
+
+    pub fn serialize<S: Serializer>(
+        (distribution, inputs): &(Distributions, DistributionInputs),
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        ParameterRef {
+            distribution,
+            inputs,
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<(Distributions, DistributionInputs), D::Error> {
+        let parameter = Parameter::deserialize(deserializer)?;
+        Ok((parameter.distribution, parameter.inputs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_config_is_valid() {
+        assert_eq!(SimulationConfig::default().validate(), Ok(()));
+    }
+}
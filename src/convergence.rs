@@ -0,0 +1,212 @@
+use serde::{Deserialize, Serialize};
+use strum_macros::EnumIter;
+
+/// Two-sided 95% standard normal quantile, used for confidence intervals.
+const Z_95: f64 = 1.959_963_984_540_054;
+
+/// Which estimate of the per-trial average monthly commission must become precise.
+#[derive(Debug, PartialEq, Clone, Copy, Default, EnumIter, Serialize, Deserialize)]
+pub enum TargetStatistic {
+    #[default]
+    Mean,
+    P10,
+}
+
+impl std::fmt::Display for TargetStatistic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TargetStatistic::Mean => write!(f, "Mean"),
+            TargetStatistic::P10 => write!(f, "P10"),
+        }
+    }
+}
+
+/// How the precision of the estimate is measured, relative to the estimate itself.
+#[derive(Debug, PartialEq, Clone, Copy, Default, EnumIter, Serialize, Deserialize)]
+pub enum PrecisionMeasure {
+    #[default]
+    StandardError,
+    /// Full width of the 95% confidence interval.
+    ConfidenceInterval,
+}
+
+impl std::fmt::Display for PrecisionMeasure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PrecisionMeasure::StandardError => write!(f, "Relative standard error"),
+            PrecisionMeasure::ConfidenceInterval => write!(f, "Relative 95% CI width"),
+        }
+    }
+}
+
+/// Stops a run once the chosen estimate is precise enough, instead of after a fixed number of
+/// trials. The configured trial count then acts as a cap.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConvergenceTarget {
+    pub statistic: TargetStatistic,
+    pub measure: PrecisionMeasure,
+    /// Largest acceptable precision, as a fraction of the estimate, e.g. `0.01` for 1%.
+    pub target: f64,
+}
+
+impl Default for ConvergenceTarget {
+    fn default() -> Self {
+        ConvergenceTarget {
+            statistic: TargetStatistic::default(),
+            measure: PrecisionMeasure::default(),
+            target: 0.01,
+        }
+    }
+}
+
+/// How precise the estimate was when a convergence-driven run stopped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvergenceReport {
+    pub target: ConvergenceTarget,
+    pub trials: usize,
+    pub estimate: f64,
+    /// Precision reached, in the same relative terms as [`ConvergenceTarget::target`].
+    pub achieved: f64,
+    /// False if the trial cap was hit before the target was met.
+    pub converged: bool,
+}
+
+impl std::fmt::Display for ConvergenceReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let outcome = if self.converged {
+            "converged after"
+        } else {
+            "did not converge within"
+        };
+        write!(
+            f,
+            "{} commission of {:.2} {} {} trials. {} {:.2}% (target {:.2}%)",
+            self.target.statistic,
+            self.estimate,
+            outcome,
+            self.trials,
+            self.target.measure,
+            self.achieved * 100.0,
+            self.target.target * 100.0
+        )
+    }
+}
+
+impl ConvergenceTarget {
+    /// Measures the estimate over the per-trial `values` gathered so far.
+    pub fn report(&self, values: &[f64]) -> ConvergenceReport {
+        let (estimate, standard_error) = match self.statistic {
+            TargetStatistic::Mean => mean_and_standard_error(values),
+            TargetStatistic::P10 => quantile_and_standard_error(values, 0.10),
+        };
+        let achieved = self.relative_precision(estimate, standard_error);
+        ConvergenceReport {
+            target: self.clone(),
+            trials: values.len(),
+            estimate,
+            achieved,
+            converged: achieved <= self.target,
+        }
+    }
+
+    /// The precision of an estimate with this standard error, as [`Self::measure`] measures it
+    /// relative to the estimate.
+    pub fn relative_precision(&self, estimate: f64, standard_error: f64) -> f64 {
+        let absolute = match self.measure {
+            PrecisionMeasure::StandardError => standard_error,
+            PrecisionMeasure::ConfidenceInterval => 2.0 * Z_95 * standard_error,
+        };
+        if absolute == 0.0 {
+            0.0
+        } else {
+            absolute / estimate.abs()
+        }
+    }
+}
+
+fn mean_and_standard_error(values: &[f64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    if values.len() < 2 {
+        return (values.first().copied().unwrap_or(f64::NAN), f64::INFINITY);
+    }
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (mean, (variance / n).sqrt())
+}
+
+/// Sample quantile with a standard error backed out of the distribution-free order-statistic
+/// 95% confidence interval, so no density estimate is needed.
+fn quantile_and_standard_error(values: &[f64], p: f64) -> (f64, f64) {
+    let n = values.len();
+    if n < 2 {
+        return (values.first().copied().unwrap_or(f64::NAN), f64::INFINITY);
+    }
+    let mut values = values.to_vec();
+    let mut order_statistic = |rank: f64| {
+        let index = (rank.max(0.0) as usize).min(n - 1);
+        *values.select_nth_unstable_by(index, f64::total_cmp).1
+    };
+    let half_width = Z_95 * (n as f64 * p * (1.0 - p)).sqrt();
+    let centre = n as f64 * p;
+    let estimate = order_statistic(p * (n - 1) as f64);
+    let lower = order_statistic((centre - half_width).floor());
+    let upper = order_statistic((centre + half_width).ceil());
+    (estimate, (upper - lower) / (2.0 * Z_95))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(statistic: TargetStatistic, target: f64) -> ConvergenceTarget {
+        ConvergenceTarget {
+            statistic,
+            measure: PrecisionMeasure::StandardError,
+            target,
+        }
+    }
+
+    #[test]
+    fn mean_converges_once_its_relative_standard_error_meets_the_target() {
+        // Mean 10, sample variance 2/3 over 4 values, so a standard error of sqrt(1/6).
+        let values = [9.0, 10.0, 10.0, 11.0];
+        let standard_error = (1.0f64 / 6.0).sqrt();
+        let report = target(TargetStatistic::Mean, 0.05).report(&values);
+        assert_eq!(report.estimate, 10.0);
+        assert!((report.achieved - standard_error / 10.0).abs() < 1e-12);
+        assert!(report.converged);
+        assert!(
+            !target(TargetStatistic::Mean, 0.04)
+                .report(&values)
+                .converged
+        );
+    }
+
+    #[test]
+    fn confidence_interval_is_the_full_95_percent_width() {
+        let measured = ConvergenceTarget {
+            measure: PrecisionMeasure::ConfidenceInterval,
+            ..Default::default()
+        };
+        let width = measured.relative_precision(10.0, 0.5);
+        assert!((width - 2.0 * Z_95 * 0.05).abs() < 1e-12);
+    }
+
+    #[test]
+    fn p10_standard_error_comes_from_the_order_statistic_interval() {
+        // For 1..=1000 the 95% interval of the P10 runs from the 82nd to the 120th value:
+        // 100 ± 1.96 sqrt(1000 · 0.1 · 0.9) spans ranks 81.4 to 118.6, counting from 0.
+        let values: Vec<f64> = (1..=1000).map(f64::from).rev().collect();
+        let (estimate, standard_error) = quantile_and_standard_error(&values, 0.10);
+        assert_eq!(estimate, 100.0);
+        assert!((standard_error - (120.0 - 82.0) / (2.0 * Z_95)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn too_few_values_never_converge() {
+        for statistic in [TargetStatistic::Mean, TargetStatistic::P10] {
+            assert!(!target(statistic, 1.0).report(&[5.0]).converged);
+        }
+    }
+}
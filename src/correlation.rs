@@ -0,0 +1,204 @@
+use crate::errors::CorrelationError;
+use ndarray::Array1;
+use ndarray_rand::rand::Rng;
+use ndarray_rand::rand_distr::StandardNormal;
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+/// Number of model inputs that can be correlated with each other.
+pub const NUM_CORRELATED_INPUTS: usize = 5;
+
+/// Number of inputs, at the start of [`CorrelatedInput`], that are drawn once per trial.
+pub const TRIAL_LEVEL_INPUTS: usize = 3;
+
+/// Tolerance used when deciding whether a Cholesky pivot is zero.
+const PIVOT_TOLERANCE: f64 = 1e-10;
+
+/// The model inputs, in the order they appear in a [`CorrelationMatrix`].
+/// Inputs drawn once per trial come first so a trial's factors can be reused by each visit.
+#[derive(Debug, PartialEq, Clone, Copy, EnumIter)]
+pub enum CorrelatedInput {
+    WorkdaysPerMonth,
+    RetailersPerDay,
+    ConversionRate,
+    Price,
+    Units,
+}
+
+impl std::fmt::Display for CorrelatedInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CorrelatedInput::WorkdaysPerMonth => write!(f, "Workdays/Month"),
+            CorrelatedInput::RetailersPerDay => write!(f, "Retailers/Day"),
+            CorrelatedInput::ConversionRate => write!(f, "Conversion Rate"),
+            CorrelatedInput::Price => write!(f, "Price"),
+            CorrelatedInput::Units => write!(f, "Units/Sale"),
+        }
+    }
+}
+
+/// Correlations between the normal scores of the model inputs, applied through a Gaussian copula.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CorrelationMatrix(pub [[f64; NUM_CORRELATED_INPUTS]; NUM_CORRELATED_INPUTS]);
+
+impl Default for CorrelationMatrix {
+    fn default() -> Self {
+        let mut matrix = [[0.0; NUM_CORRELATED_INPUTS]; NUM_CORRELATED_INPUTS];
+        for (i, row) in matrix.iter_mut().enumerate() {
+            row[i] = 1.0;
+        }
+        CorrelationMatrix(matrix)
+    }
+}
+
+impl CorrelationMatrix {
+    pub fn get(&self, a: CorrelatedInput, b: CorrelatedInput) -> f64 {
+        self.0[a as usize][b as usize]
+    }
+
+    /// Sets the correlation between `a` and `b`, keeping the matrix symmetric.
+    pub fn set(&mut self, a: CorrelatedInput, b: CorrelatedInput, value: f64) {
+        self.0[a as usize][b as usize] = value;
+        self.0[b as usize][a as usize] = value;
+    }
+
+    /// True when every input is independent, in which case no copula is needed.
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Factorises the matrix as `L Lᵀ`. Zero pivots are allowed so that positive
+    /// semi-definite matrices, such as ones with perfectly correlated inputs, are accepted.
+    pub fn cholesky(&self) -> Result<CholeskyFactor, CorrelationError> {
+        let a = &self.0;
+        for (i, row_input) in CorrelatedInput::iter().enumerate() {
+            if a[i][i] != 1.0 {
+                return Err(CorrelationError::DiagonalNotOne(row_input));
+            }
+            for (j, column_input) in CorrelatedInput::iter().enumerate().take(i) {
+                if !(-1.0..=1.0).contains(&a[i][j]) {
+                    return Err(CorrelationError::OutOfRange(row_input, column_input));
+                }
+                if a[i][j] != a[j][i] {
+                    return Err(CorrelationError::NotSymmetric(row_input, column_input));
+                }
+            }
+        }
+
+        let mut l = [[0.0; NUM_CORRELATED_INPUTS]; NUM_CORRELATED_INPUTS];
+        for j in 0..NUM_CORRELATED_INPUTS {
+            let pivot = a[j][j] - (0..j).map(|k| l[j][k] * l[j][k]).sum::<f64>();
+            if pivot < -PIVOT_TOLERANCE {
+                return Err(CorrelationError::NotPositiveSemiDefinite);
+            }
+            let diagonal = pivot.max(0.0).sqrt();
+            l[j][j] = diagonal;
+            for i in (j + 1)..NUM_CORRELATED_INPUTS {
+                let off_diagonal = a[i][j] - (0..j).map(|k| l[i][k] * l[j][k]).sum::<f64>();
+                if diagonal > PIVOT_TOLERANCE {
+                    l[i][j] = off_diagonal / diagonal;
+                } else if off_diagonal.abs() > PIVOT_TOLERANCE {
+                    return Err(CorrelationError::NotPositiveSemiDefinite);
+                }
+            }
+        }
+        Ok(CholeskyFactor(l))
+    }
+}
+
+/// Lower-triangular factor `L` of a [`CorrelationMatrix`], used to turn independent standard
+/// normals `e` into correlated ones `z = L e`.
+///
+/// Because the trial-level inputs come first, the trial-level part of `z` only depends on the
+/// trial-level part of `e`. Reusing that part for every visit and drawing fresh normals for the
+/// visit-level inputs gives each visit the full correlation structure.
+#[derive(Debug, Clone)]
+pub struct CholeskyFactor([[f64; NUM_CORRELATED_INPUTS]; NUM_CORRELATED_INPUTS]);
+
+impl CholeskyFactor {
+    /// Draws the independent normals for one trial.
+    pub fn trial_factors<R: Rng>(&self, rng: &mut R) -> [f64; TRIAL_LEVEL_INPUTS] {
+        std::array::from_fn(|_| rng.sample(StandardNormal))
+    }
+
+    /// Correlated standard normal for a trial-level input.
+    pub fn trial_normal(
+        &self,
+        input: CorrelatedInput,
+        trial_factors: &[f64; TRIAL_LEVEL_INPUTS],
+    ) -> f64 {
+        let row = &self.0[input as usize];
+        (0..TRIAL_LEVEL_INPUTS)
+            .map(|k| row[k] * trial_factors[k])
+            .sum()
+    }
+
+    /// Correlated standard normals for the visit-level inputs of `n` visits,
+    /// returned as one array per input in [`CorrelatedInput`] order.
+    pub fn visit_normals<R: Rng>(
+        &self,
+        trial_factors: &[f64; TRIAL_LEVEL_INPUTS],
+        n: usize,
+        rng: &mut R,
+    ) -> [Array1<f64>; NUM_CORRELATED_INPUTS - TRIAL_LEVEL_INPUTS] {
+        let mut normals = std::array::from_fn(|_| Array1::<f64>::zeros(n));
+        let mut factors = [0.0; NUM_CORRELATED_INPUTS];
+        factors[..TRIAL_LEVEL_INPUTS].copy_from_slice(trial_factors);
+        for v in 0..n {
+            for factor in factors.iter_mut().skip(TRIAL_LEVEL_INPUTS) {
+                *factor = rng.sample(StandardNormal);
+            }
+            for (offset, array) in normals.iter_mut().enumerate() {
+                let row = &self.0[TRIAL_LEVEL_INPUTS + offset];
+                array[v] = row.iter().zip(factors.iter()).map(|(l, e)| l * e).sum();
+            }
+        }
+        normals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use CorrelatedInput::*;
+
+    #[test]
+    fn cholesky_factor_reproduces_the_matrix() {
+        let mut matrix = CorrelationMatrix::default();
+        matrix.set(WorkdaysPerMonth, RetailersPerDay, 0.5);
+        matrix.set(RetailersPerDay, ConversionRate, 0.3);
+        matrix.set(Price, Units, -0.4);
+        let CholeskyFactor(l) = matrix.cholesky().unwrap();
+        assert!((l[1][0] - 0.5).abs() < 1e-12);
+        assert!((l[1][1] - 0.75f64.sqrt()).abs() < 1e-12);
+        for i in 0..NUM_CORRELATED_INPUTS {
+            for j in 0..NUM_CORRELATED_INPUTS {
+                let product: f64 = (0..NUM_CORRELATED_INPUTS).map(|k| l[i][k] * l[j][k]).sum();
+                assert!((product - matrix.0[i][j]).abs() < 1e-12, "L Lᵀ[{i}][{j}]");
+            }
+        }
+    }
+
+    #[test]
+    fn perfectly_correlated_inputs_are_accepted() {
+        let mut matrix = CorrelationMatrix::default();
+        matrix.set(Price, Units, 1.0);
+        let CholeskyFactor(l) = matrix.cholesky().unwrap();
+        assert_eq!(l[Units as usize][Price as usize], 1.0);
+        assert_eq!(l[Units as usize][Units as usize], 0.0);
+    }
+
+    #[test]
+    fn matrix_that_is_not_positive_semi_definite_is_rejected() {
+        // Two inputs each strongly correlated with a third cannot be strongly anti-correlated.
+        let mut matrix = CorrelationMatrix::default();
+        matrix.set(WorkdaysPerMonth, RetailersPerDay, 0.9);
+        matrix.set(WorkdaysPerMonth, ConversionRate, 0.9);
+        matrix.set(RetailersPerDay, ConversionRate, -0.9);
+        assert!(matches!(
+            matrix.cholesky(),
+            Err(CorrelationError::NotPositiveSemiDefinite)
+        ));
+    }
+}
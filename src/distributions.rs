@@ -1,6 +1,7 @@
+use serde::{Deserialize, Serialize};
 use strum_macros::EnumIter;
 
-#[derive(Debug, PartialEq, Clone, Copy, Default, EnumIter)]
+#[derive(Debug, PartialEq, Clone, Copy, Default, EnumIter, Serialize, Deserialize)]
 pub enum Distributions {
     Uniform,
     #[default]
@@ -9,6 +10,17 @@ pub enum Distributions {
     Constant,
     Triangular,
     Pert,
+    LogNormal,
+    Gamma,
+    Beta,
+    Weibull,
+    Poisson,
+    NegativeBinomial,
+    Binomial,
+    DiscreteUniform,
+    DiscreteTable,
+    Empirical,
+    Metalog,
 }
 
 impl std::fmt::Display for Distributions {
@@ -20,11 +32,37 @@ impl std::fmt::Display for Distributions {
             Distributions::Constant => write!(f, "Constant"),
             Distributions::Triangular => write!(f, "Triangular"),
             Distributions::Pert => write!(f, "Pert"),
+            Distributions::LogNormal => write!(f, "LogNormal"),
+            Distributions::Gamma => write!(f, "Gamma"),
+            Distributions::Beta => write!(f, "Beta"),
+            Distributions::Weibull => write!(f, "Weibull"),
+            Distributions::Poisson => write!(f, "Poisson"),
+            Distributions::NegativeBinomial => write!(f, "Negative Binomial"),
+            Distributions::Binomial => write!(f, "Binomial"),
+            Distributions::DiscreteUniform => write!(f, "Discrete Uniform"),
+            Distributions::DiscreteTable => write!(f, "Discrete Table"),
+            Distributions::Empirical => write!(f, "Empirical"),
+            Distributions::Metalog => write!(f, "Metalog"),
         }
     }
 }
 
-#[derive(Default, Debug, Clone)]
+impl Distributions {
+    /// True for distributions whose draws are whole numbers.
+    pub fn is_discrete(&self) -> bool {
+        matches!(
+            self,
+            Distributions::Bernoulli
+                | Distributions::Poisson
+                | Distributions::NegativeBinomial
+                | Distributions::Binomial
+                | Distributions::DiscreteUniform
+        )
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct DistributionInputs {
     pub bernoulli_prob: f64,
     pub normal_mean: f64,
@@ -38,4 +76,74 @@ pub struct DistributionInputs {
     pub pert_max: f64,
     pub pert_min: f64,
     pub pert_mode: f64,
+    /// Mean of the logarithm of a LogNormal variable.
+    pub lognormal_mu: f64,
+    /// Standard deviation of the logarithm of a LogNormal variable.
+    pub lognormal_sigma: f64,
+    pub gamma_shape: f64,
+    pub gamma_scale: f64,
+    /// Beta shape parameters; the Beta variable is rescaled from [0, 1] to [min, max].
+    pub beta_alpha: f64,
+    pub beta_beta: f64,
+    pub beta_min: f64,
+    pub beta_max: f64,
+    pub weibull_shape: f64,
+    pub weibull_scale: f64,
+    pub poisson_lambda: f64,
+    /// Number of successes a Negative Binomial counts failures up to; need not be whole.
+    pub negbinomial_r: f64,
+    /// Success probability of each Negative Binomial trial.
+    pub negbinomial_p: f64,
+    pub binomial_n: u64,
+    pub binomial_p: f64,
+    /// Inclusive bounds of a Discrete Uniform.
+    pub discrete_min: i64,
+    pub discrete_max: i64,
+    /// Values of a Discrete Table and their relative weights.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub discrete_table: Vec<TableRow>,
+    /// CSV or parquet file an Empirical distribution draws its values from.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub empirical_path: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub empirical_column: String,
+    /// Smooths an Empirical distribution with a Gaussian kernel instead of only redrawing
+    /// the observed values.
+    pub empirical_smoothing: bool,
+    /// Quantiles a Metalog passes through, one term per point.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub metalog_points: Vec<MetalogPoint>,
+    /// Limits of a Metalog's support; leaving either out makes it unbounded on that side.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metalog_lower: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metalog_upper: Option<f64>,
+    /// Truncates the distribution to values at or above this bound.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lower_bound: Option<f64>,
+    /// Truncates the distribution to values at or below this bound.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upper_bound: Option<f64>,
+}
+
+/// One value of a Discrete Table. Weights need not sum to one; each value is drawn with
+/// probability proportional to its weight.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TableRow {
+    pub value: f64,
+    pub weight: f64,
+}
+
+/// One quantile of a Metalog: the value with `probability` of a draw falling below it.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetalogPoint {
+    pub probability: f64,
+    pub value: f64,
+}
+
+impl DistributionInputs {
+    /// True if either truncation bound is set.
+    pub fn is_truncated(&self) -> bool {
+        self.lower_bound.is_some() || self.upper_bound.is_some()
+    }
 }
@@ -0,0 +1,194 @@
+use crate::errors::EmpiricalError;
+use ndarray_rand::rand::Rng;
+use ndarray_rand::rand_distr::StandardNormal;
+use polars::prelude::*;
+use statrs::distribution::{ContinuousCDF, Normal};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock, Mutex, OnceLock},
+    time::SystemTime,
+};
+
+/// Points at which the smoothed CDF is tabulated for its inverse.
+const KDE_GRID_POINTS: usize = 512;
+
+/// Bandwidths beyond the data the smoothed CDF is tabulated over, by which point it is flat.
+const KDE_GRID_MARGIN: f64 = 4.0;
+
+/// A file and column, and the file's modification time so that edits to it are picked up.
+type CacheKey = (PathBuf, String, Option<SystemTime>);
+
+/// Loads by file and column, failed ones included so that a bad file is not reread every frame.
+type Cache<T> = LazyLock<Mutex<HashMap<CacheKey, Result<T, EmpiricalError>>>>;
+
+/// Every column loaded so far. Trials sample from the same data thousands of times, and the
+/// GUI redraws it every frame, so each file is read once rather than on every use.
+static DATA: Cache<Arc<EmpiricalData>> = LazyLock::new(Default::default);
+
+static COLUMNS: Cache<Vec<String>> = LazyLock::new(Default::default);
+
+/// Observed values of one column of a data file.
+#[derive(Debug)]
+pub struct EmpiricalData {
+    /// The column's finite values, sorted.
+    pub values: Vec<f64>,
+    /// Width of the Gaussian kernel used for smoothing, by Silverman's rule of thumb.
+    pub bandwidth: f64,
+    /// `[x, F(x)]` of the smoothed distribution, worked out the first time it is needed.
+    smoothed_cdf: OnceLock<Vec<[f64; 2]>>,
+}
+
+impl EmpiricalData {
+    fn new(mut values: Vec<f64>) -> Result<Self, EmpiricalError> {
+        values.retain(|x| x.is_finite());
+        if values.is_empty() {
+            return Err(EmpiricalError::NoData);
+        }
+        values.sort_by(f64::total_cmp);
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let std = (values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n).sqrt();
+        let quartile = |p: f64| values[((p * (n - 1.0)).round() as usize).min(values.len() - 1)];
+        let spread = (quartile(0.75) - quartile(0.25)) / 1.34;
+        // A column whose middle half is a single value still has a spread to smooth over.
+        let spread = if spread > 0.0 { spread.min(std) } else { std };
+        Ok(EmpiricalData {
+            values,
+            bandwidth: 0.9 * spread * n.powf(-0.2),
+            smoothed_cdf: OnceLock::new(),
+        })
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.values.iter().sum::<f64>() / self.values.len() as f64
+    }
+
+    /// Draws one observed value at random, plus Gaussian kernel noise if `smoothed`.
+    pub fn sample<R: Rng>(&self, smoothed: bool, rng: &mut R) -> f64 {
+        let value = self.values[rng.gen_range(0..self.values.len())];
+        if smoothed {
+            value + self.bandwidth * rng.sample::<f64, _>(StandardNormal)
+        } else {
+            value
+        }
+    }
+
+    /// Inverse CDF at `u` of the values, or of their kernel density estimate if `smoothed`.
+    pub fn quantile(&self, u: f64, smoothed: bool) -> f64 {
+        if !smoothed || self.bandwidth <= 0.0 {
+            let index = (u * self.values.len() as f64) as usize;
+            return self.values[index.min(self.values.len() - 1)];
+        }
+        let cdf = self
+            .smoothed_cdf
+            .get_or_init(|| self.tabulate_smoothed_cdf());
+        let above = cdf.partition_point(|[_, p]| *p < u).clamp(1, cdf.len() - 1);
+        let ([x0, p0], [x1, p1]) = (cdf[above - 1], cdf[above]);
+        if p1 > p0 {
+            x0 + (u - p0) / (p1 - p0) * (x1 - x0)
+        } else {
+            x0
+        }
+    }
+
+    fn tabulate_smoothed_cdf(&self) -> Vec<[f64; 2]> {
+        let kernel = Normal::standard();
+        let min = self.values[0] - KDE_GRID_MARGIN * self.bandwidth;
+        let max = self.values[self.values.len() - 1] + KDE_GRID_MARGIN * self.bandwidth;
+        let step = (max - min) / (KDE_GRID_POINTS - 1) as f64;
+        let n = self.values.len() as f64;
+        (0..KDE_GRID_POINTS)
+            .map(|i| {
+                let x = min + i as f64 * step;
+                let p = self
+                    .values
+                    .iter()
+                    .map(|value| kernel.cdf((x - value) / self.bandwidth))
+                    .sum::<f64>()
+                    / n;
+                [x, p]
+            })
+            .collect()
+    }
+}
+
+/// Loads `column` of the CSV or parquet file at `path`, reusing an earlier load unless the file
+/// has changed since. Files ending in `.parquet` are read as parquet, anything else as CSV with
+/// a header row. Missing values are dropped.
+pub fn load(path: &Path, column: &str) -> Result<Arc<EmpiricalData>, EmpiricalError> {
+    cached(&DATA, path, column, || {
+        let df = scan(path)?
+            .select([col(column).cast(DataType::Float64)])
+            .drop_nulls(None)
+            .collect()
+            .map_err(|e| read_error(e, column))?;
+        let values = df
+            .column(column)
+            .and_then(|values| {
+                values
+                    .f64()
+                    .map(|values| values.into_no_null_iter().collect())
+            })
+            .map_err(|e| read_error(e, column))?;
+        Ok(Arc::new(EmpiricalData::new(values)?))
+    })
+}
+
+/// Names of the numeric columns of the CSV or parquet file at `path`.
+pub fn numeric_columns(path: &Path) -> Result<Vec<String>, EmpiricalError> {
+    cached(&COLUMNS, path, "", || {
+        let schema = scan(path)?
+            .collect_schema()
+            .map_err(|e| EmpiricalError::Read(e.to_string()))?;
+        Ok(schema
+            .iter()
+            .filter(|(_, dtype)| dtype.is_primitive_numeric())
+            .map(|(name, _)| name.to_string())
+            .collect())
+    })
+}
+
+fn cached<T: Clone>(
+    cache: &Mutex<HashMap<CacheKey, Result<T, EmpiricalError>>>,
+    path: &Path,
+    column: &str,
+    read: impl FnOnce() -> Result<T, EmpiricalError>,
+) -> Result<T, EmpiricalError> {
+    let modified = fs::metadata(path)
+        .map_err(|e| EmpiricalError::Read(e.to_string()))?
+        .modified()
+        .ok();
+    let key = (path.to_path_buf(), column.to_string(), modified);
+    if let Some(result) = cache.lock().unwrap().get(&key) {
+        return result.clone();
+    }
+    let result = read();
+    let mut cache = cache.lock().unwrap();
+    cache.retain(|(cached_path, cached_column, _), _| {
+        (cached_path, cached_column) != (&key.0, &key.1)
+    });
+    cache.insert(key, result.clone());
+    result
+}
+
+fn scan(path: &Path) -> Result<LazyFrame, EmpiricalError> {
+    let location = PlPath::new(&path.to_string_lossy());
+    let lf = if path
+        .extension()
+        .is_some_and(|extension| extension == "parquet")
+    {
+        LazyFrame::scan_parquet(location, ScanArgsParquet::default())
+    } else {
+        LazyCsvReader::new(location).with_has_header(true).finish()
+    };
+    lf.map_err(|e| EmpiricalError::Read(e.to_string()))
+}
+
+fn read_error(e: PolarsError, column: &str) -> EmpiricalError {
+    match e {
+        PolarsError::ColumnNotFound(_) => EmpiricalError::MissingColumn(column.to_string()),
+        e => EmpiricalError::Read(e.to_string()),
+    }
+}
@@ -1,13 +1,186 @@
-use ndarray_rand::rand_distr::{BernoulliError, NormalError, PertError, TriangularError};
+use crate::correlation::CorrelatedInput;
+use crate::distributions::Distributions;
+use crate::validation::Field;
+use ndarray_rand::rand_distr::{
+    BernoulliError, BetaError, BinomialError, GammaError, NormalError, PertError, PoissonError,
+    TriangularError, WeibullError, WeightedError,
+};
 use polars::error::PolarsError;
+use std::path::{Path, PathBuf};
 use strum_macros::Display;
 
-#[derive(Debug, Display)]
+#[derive(Debug, Clone, Display)]
 pub enum DistributionError {
+    #[strum(to_string = "Invalid Normal parameters: {0}")]
     Normal(NormalError),
+    #[strum(to_string = "Invalid Bernoulli probability: {0}")]
     Bernoulli(BernoulliError),
+    #[strum(to_string = "Invalid PERT parameters: {0}")]
     Pert(PertError),
+    #[strum(to_string = "Invalid Triangular parameters: {0}")]
     Triangular(TriangularError),
+    #[strum(to_string = "Invalid LogNormal parameters: {0}")]
+    LogNormal(NormalError),
+    #[strum(to_string = "Invalid Gamma parameters: {0}")]
+    Gamma(GammaError),
+    #[strum(to_string = "Invalid Beta parameters: {0}")]
+    Beta(BetaError),
+    /// The Beta distribution's min is not below its max.
+    #[strum(to_string = "The Beta distribution's min must be below its max.")]
+    BetaRange,
+    #[strum(to_string = "Invalid Weibull parameters: {0}")]
+    Weibull(WeibullError),
+    #[strum(to_string = "Invalid Poisson rate: {0}")]
+    Poisson(PoissonError),
+    /// The Negative Binomial needs r > 0 and 0 < p < 1.
+    #[strum(to_string = "The Negative Binomial needs r above 0 and p strictly between 0 and 1.")]
+    NegativeBinomial,
+    #[strum(to_string = "Invalid Binomial parameters: {0}")]
+    Binomial(BinomialError),
+    /// The Discrete Uniform's min is above its max.
+    #[strum(to_string = "The Discrete Uniform's min must not be above its max.")]
+    DiscreteUniform,
+    #[strum(to_string = "Invalid Discrete Table weights: {0}")]
+    DiscreteTable(WeightedError),
+    #[strum(to_string = "{0}")]
+    Empirical(EmpiricalError),
+    #[strum(to_string = "{0}")]
+    Metalog(MetalogError),
+    /// The lower truncation bound is above the upper one.
+    #[strum(to_string = "The lower bound must not be above the upper bound.")]
+    Bounds,
+    /// No probability lies between the truncation bounds.
+    #[strum(to_string = "No probability lies between the lower and upper bounds.")]
+    Truncation,
+    #[strum(to_string = "Could not compute the inverse CDF: {0}")]
+    InverseCdf(String),
+}
+
+/// Why an Empirical distribution's data could not be loaded.
+#[derive(Debug, Clone)]
+pub enum EmpiricalError {
+    Read(String),
+    MissingColumn(String),
+    /// The column has no finite values.
+    NoData,
+}
+
+impl std::fmt::Display for EmpiricalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EmpiricalError::Read(e) => write!(f, "Could not read the data file: {}", e),
+            EmpiricalError::MissingColumn(column) => {
+                write!(f, "The data file has no column named {}.", column)
+            }
+            EmpiricalError::NoData => write!(f, "The column has no numeric values."),
+        }
+    }
+}
+
+/// Why a Metalog could not be fitted through its quantiles.
+#[derive(Debug, Clone)]
+pub enum MetalogError {
+    TooFewPoints,
+    /// A probability is not strictly between 0 and 1.
+    Probability,
+    /// Probabilities repeat, or values do not increase with them.
+    NotIncreasing,
+    /// The lower limit is not below the upper one.
+    Limits,
+    OutsideLimits,
+    /// The quantile function through the points decreases somewhere.
+    Infeasible,
+}
+
+impl std::fmt::Display for MetalogError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MetalogError::TooFewPoints => write!(f, "A Metalog needs at least two quantiles."),
+            MetalogError::Probability => {
+                write!(f, "Metalog probabilities must be strictly between 0 and 1.")
+            }
+            MetalogError::NotIncreasing => write!(
+                f,
+                "Metalog probabilities must differ, and values must increase with them."
+            ),
+            MetalogError::Limits => write!(
+                f,
+                "The Metalog's lower limit must be below its upper limit."
+            ),
+            MetalogError::OutsideLimits => write!(
+                f,
+                "Every Metalog value must lie strictly between its limits."
+            ),
+            MetalogError::Infeasible => write!(
+                f,
+                "No Metalog passes through these quantiles: its quantile function would decrease somewhere. Try fewer or smoother quantiles."
+            ),
+        }
+    }
+}
+
+/// Why a distribution could not be set from a P10, P50 and P90.
+#[derive(Debug)]
+pub enum QuantileError {
+    Unsupported(Distributions),
+    /// The quantiles are not in strictly increasing order.
+    NotIncreasing,
+    /// A positive-only distribution was given a P10 at or below zero.
+    NotPositive(Distributions),
+    NoSolution(Distributions),
+}
+
+impl std::fmt::Display for QuantileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuantileError::Unsupported(distribution) => write!(
+                f,
+                "{} distributions cannot be set from quantiles.",
+                distribution
+            ),
+            QuantileError::NotIncreasing => write!(f, "P10, P50 and P90 must be increasing."),
+            QuantileError::NotPositive(distribution) => {
+                write!(f, "{} distributions need a P10 above zero.", distribution)
+            }
+            QuantileError::NoSolution(distribution) => write!(
+                f,
+                "No {} distribution within its bounds comes near these quantiles.",
+                distribution
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CorrelationError {
+    DiagonalNotOne(CorrelatedInput),
+    OutOfRange(CorrelatedInput, CorrelatedInput),
+    NotSymmetric(CorrelatedInput, CorrelatedInput),
+    NotPositiveSemiDefinite,
+}
+
+impl std::fmt::Display for CorrelationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CorrelationError::DiagonalNotOne(input) => {
+                write!(f, "The correlation of {} with itself must be 1.", input)
+            }
+            CorrelationError::OutOfRange(a, b) => write!(
+                f,
+                "The correlation between {} and {} must be between -1 and 1.",
+                a, b
+            ),
+            CorrelationError::NotSymmetric(a, b) => write!(
+                f,
+                "The correlation between {} and {} differs from that between {} and {}.",
+                a, b, b, a
+            ),
+            CorrelationError::NotPositiveSemiDefinite => write!(
+                f,
+                "The correlation matrix is not positive semi-definite, so these correlations cannot all hold at once."
+            ),
+        }
+    }
 }
 
 impl From<PertError> for DistributionError {
@@ -30,9 +203,262 @@ impl From<TriangularError> for DistributionError {
         DistributionError::Triangular(e)
     }
 }
-impl From<DistributionError> for PolarsError {
-    fn from(err: DistributionError) -> Self {
-        // We wrap your error's text inside a PolarsError::ComputeError
-        PolarsError::ComputeError(err.to_string().into())
+impl From<GammaError> for DistributionError {
+    fn from(e: GammaError) -> DistributionError {
+        DistributionError::Gamma(e)
+    }
+}
+impl From<BetaError> for DistributionError {
+    fn from(e: BetaError) -> DistributionError {
+        DistributionError::Beta(e)
+    }
+}
+impl From<WeibullError> for DistributionError {
+    fn from(e: WeibullError) -> DistributionError {
+        DistributionError::Weibull(e)
+    }
+}
+impl From<PoissonError> for DistributionError {
+    fn from(e: PoissonError) -> DistributionError {
+        DistributionError::Poisson(e)
+    }
+}
+impl From<BinomialError> for DistributionError {
+    fn from(e: BinomialError) -> DistributionError {
+        DistributionError::Binomial(e)
+    }
+}
+impl From<WeightedError> for DistributionError {
+    fn from(e: WeightedError) -> DistributionError {
+        DistributionError::DiscreteTable(e)
+    }
+}
+impl From<EmpiricalError> for DistributionError {
+    fn from(e: EmpiricalError) -> DistributionError {
+        DistributionError::Empirical(e)
+    }
+}
+impl From<MetalogError> for DistributionError {
+    fn from(e: MetalogError) -> DistributionError {
+        DistributionError::Metalog(e)
+    }
+}
+/// What a field of the model's inputs must satisfy but does not.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Requirement {
+    Finite,
+    Positive,
+    NonNegative,
+    /// Inclusive range.
+    Within(f64, f64),
+    /// Exclusive range.
+    StrictlyWithin(f64, f64),
+    /// Not below the named field.
+    AtLeast(&'static str),
+    /// Not above the named field.
+    AtMost(&'static str),
+    /// Strictly above the named field.
+    Above(&'static str),
+    NonEmpty,
+    NonNegativeWeights,
+    PositiveTotalWeight,
+    /// The truncation bounds must leave some probability between them.
+    ProbabilityWithinBounds,
+    MedianWithin(f64, f64),
+    /// Only holds when the inputs are not correlated.
+    IndependentInputs,
+    /// Must be turned off for the named analysis.
+    OffFor(&'static str),
+    /// A check with its own explanation, such as a Metalog's or the correlation matrix's.
+    Other(String),
+}
+
+impl std::fmt::Display for Requirement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Requirement::Finite => write!(f, "must be a finite number"),
+            Requirement::Positive => write!(f, "must be above 0"),
+            Requirement::NonNegative => write!(f, "must not be negative"),
+            Requirement::Within(low, high) => write!(f, "must be between {} and {}", low, high),
+            Requirement::StrictlyWithin(low, high) => {
+                write!(f, "must be strictly between {} and {}", low, high)
+            }
+            Requirement::AtLeast(other) => write!(f, "must not be below {}", other),
+            Requirement::AtMost(other) => write!(f, "must not be above {}", other),
+            Requirement::Above(other) => write!(f, "must be above {}", other),
+            Requirement::NonEmpty => write!(f, "must not be empty"),
+            Requirement::NonNegativeWeights => write!(f, "must have no negative weights"),
+            Requirement::PositiveTotalWeight => write!(f, "must have weights summing to above 0"),
+            Requirement::ProbabilityWithinBounds => {
+                write!(f, "must leave some probability between the bounds")
+            }
+            Requirement::MedianWithin(low, high) => {
+                write!(f, "must have its median between {} and {}", low, high)
+            }
+            Requirement::IndependentInputs => {
+                write!(f, "needs independent inputs; turn off input correlation")
+            }
+            Requirement::OffFor(analysis) => write!(f, "must be off for {}", analysis),
+            Requirement::Other(reason) => write!(f, "is invalid: {}", reason),
+        }
+    }
+}
+
+/// One field of the model's inputs and the requirement it breaks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidField {
+    pub field: Field,
+    pub requirement: Requirement,
+}
+
+impl std::fmt::Display for InvalidField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.field, self.requirement)
+    }
+}
+
+/// Every field that failed validation, in the order they were checked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError(pub Vec<InvalidField>);
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fields: Vec<String> = self.0.iter().map(|field| field.to_string()).collect();
+        write!(f, "{}.", fields.join(".\n"))
+    }
+}
+
+/// Why a simulation, or an analysis built on one, failed.
+#[derive(Debug, Clone)]
+pub enum SimulationError {
+    /// The configuration breaks the requirements listed.
+    Config(ValidationError),
+    /// An input could not be drawn, in trial `trial` if it failed while drawing one.
+    Sampling {
+        input: CorrelatedInput,
+        trial: Option<i64>,
+        source: DistributionError,
+    },
+    /// Building, aggregating or summarising the trials' data frames failed.
+    Aggregation(PolarsError),
+    /// Writing an output file failed.
+    Io {
+        path: PathBuf,
+        source: PolarsError,
+    },
+    Cancelled,
+}
+
+impl SimulationError {
+    /// The fields to blame and why, where the error lies with the inputs.
+    pub fn invalid_fields(&self) -> Vec<InvalidField> {
+        match self {
+            SimulationError::Config(e) => e.0.clone(),
+            SimulationError::Sampling { input, .. } => vec![InvalidField {
+                field: Field::Input(*input, "distribution"),
+                requirement: Requirement::Other(self.to_string()),
+            }],
+            _ => Vec::new(),
+        }
+    }
+
+    pub(crate) fn io(path: &Path, source: impl Into<PolarsError>) -> SimulationError {
+        SimulationError::Io {
+            path: path.to_path_buf(),
+            source: source.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SimulationError::Config(e) => write!(f, "{}", e),
+            SimulationError::Sampling {
+                input,
+                trial: Some(trial),
+                source,
+            } => write!(f, "Could not draw {} in trial {}: {}", input, trial, source),
+            SimulationError::Sampling {
+                input,
+                trial: None,
+                source,
+            } => write!(f, "Could not draw {}: {}", input, source),
+            SimulationError::Aggregation(e) => write!(f, "Could not aggregate the trials: {}", e),
+            SimulationError::Io { path, source } => {
+                write!(f, "Could not write {}: {}", path.display(), source)
+            }
+            SimulationError::Cancelled => write!(f, "The simulation was cancelled."),
+        }
+    }
+}
+
+impl From<ValidationError> for SimulationError {
+    fn from(e: ValidationError) -> SimulationError {
+        SimulationError::Config(e)
+    }
+}
+impl From<CorrelationError> for SimulationError {
+    fn from(e: CorrelationError) -> SimulationError {
+        SimulationError::Config(ValidationError(vec![InvalidField {
+            field: Field::Correlation,
+            requirement: Requirement::Other(e.to_string()),
+        }]))
+    }
+}
+impl From<PolarsError> for SimulationError {
+    fn from(e: PolarsError) -> SimulationError {
+        SimulationError::Aggregation(e)
+    }
+}
+
+#[derive(Debug)]
+pub enum ScenarioError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Toml(toml::de::Error),
+    TomlWrite(toml::ser::Error),
+    InvalidVersion,
+    NewerVersion(u32),
+}
+
+impl std::fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScenarioError::Io(e) => write!(f, "Could not read the scenario file: {}", e),
+            ScenarioError::Json(e) => write!(f, "Invalid JSON scenario: {}", e),
+            ScenarioError::Toml(e) => write!(f, "Invalid TOML scenario: {}", e),
+            ScenarioError::TomlWrite(e) => write!(f, "Could not write the scenario as TOML: {}", e),
+            ScenarioError::InvalidVersion => {
+                write!(f, "The scenario's version must be a positive whole number.")
+            }
+            ScenarioError::NewerVersion(version) => write!(
+                f,
+                "The scenario uses format version {}, but this build of SANDHIYA only understands up to version {}.",
+                version,
+                crate::scenario::SCENARIO_VERSION
+            ),
+        }
+    }
+}
+
+impl From<std::io::Error> for ScenarioError {
+    fn from(e: std::io::Error) -> ScenarioError {
+        ScenarioError::Io(e)
+    }
+}
+impl From<serde_json::Error> for ScenarioError {
+    fn from(e: serde_json::Error) -> ScenarioError {
+        ScenarioError::Json(e)
+    }
+}
+impl From<toml::de::Error> for ScenarioError {
+    fn from(e: toml::de::Error) -> ScenarioError {
+        ScenarioError::Toml(e)
+    }
+}
+impl From<toml::ser::Error> for ScenarioError {
+    fn from(e: toml::ser::Error) -> ScenarioError {
+        ScenarioError::TomlWrite(e)
     }
 }
@@ -0,0 +1,207 @@
+use crate::charts;
+use eframe::egui;
+use sandhiya::correlation::CorrelatedInput;
+use sandhiya::distributions::{DistributionInputs, Distributions};
+use sandhiya::empirical::{self, EmpiricalData};
+use sandhiya::fitting::{Fit, FitCriterion, fit_all, rank};
+use std::{path::Path, sync::Arc};
+use strum::IntoEnumIterator;
+
+/// Fits distributions to a data column for one model input, so the user can pick one.
+pub struct DistributionFitter {
+    pub input: CorrelatedInput,
+    path: String,
+    column: String,
+    criterion: FitCriterion,
+    /// None until the user asks for a fit.
+    fits: Option<Vec<Fit>>,
+}
+
+impl DistributionFitter {
+    /// Starts from the data file of `inputs`, if it is an Empirical distribution's.
+    pub fn new(input: CorrelatedInput, inputs: &DistributionInputs) -> Self {
+        Self {
+            input,
+            path: inputs.empirical_path.clone(),
+            column: inputs.empirical_column.clone(),
+            criterion: FitCriterion::default(),
+            fits: None,
+        }
+    }
+
+    /// Renders the data picker and the ranked fits. Returns the fit the user chose, if any.
+    pub fn show(&mut self, ui: &mut egui::Ui) -> Option<Fit> {
+        let data = show_data_picker(ui, &mut self.path, &mut self.column);
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(data.is_some(), egui::Button::new("Fit distributions"))
+                .clicked()
+                && let Some(data) = &data
+            {
+                let mut fits = fit_all(&data.values);
+                rank(&mut fits, self.criterion);
+                self.fits = Some(fits);
+            }
+            ui.label("Rank by");
+            let previous = self.criterion;
+            egui::ComboBox::from_id_salt("fit_criterion")
+                .selected_text(self.criterion.to_string())
+                .show_ui(ui, |ui| {
+                    for option in FitCriterion::iter() {
+                        ui.selectable_value(&mut self.criterion, option, option.to_string());
+                    }
+                });
+            if self.criterion != previous
+                && let Some(fits) = &mut self.fits
+            {
+                rank(fits, self.criterion);
+            }
+        });
+
+        let fits = self.fits.as_ref()?;
+        if fits.is_empty() {
+            ui.label("Nothing can be fitted to fewer than two distinct values.");
+            return None;
+        }
+        let mut chosen = None;
+        egui::Grid::new("fits").striped(true).show(ui, |ui| {
+            for header in ["Distribution", "Parameters", "AIC", "K-S", "A-D", ""] {
+                ui.label(header);
+            }
+            ui.end_row();
+            for fit in fits {
+                ui.label(fit.distribution.to_string());
+                ui.label(describe(fit.distribution, &fit.inputs));
+                ui.label(format!("{:.1}", fit.aic));
+                ui.label(format!("{:.4}", fit.kolmogorov_smirnov));
+                ui.label(format!("{:.3}", fit.anderson_darling));
+                if ui.button("Use").clicked() {
+                    chosen = Some(fit.clone());
+                }
+                ui.end_row();
+            }
+        });
+        chosen
+    }
+}
+
+/// Picks a CSV or parquet file and one of its numeric columns, and previews the column.
+/// Returns the column's data once it has loaded.
+pub fn show_data_picker(
+    ui: &mut egui::Ui,
+    path: &mut String,
+    column: &mut String,
+) -> Option<Arc<EmpiricalData>> {
+    ui.horizontal(|ui| {
+        ui.label("File");
+        ui.text_edit_singleline(path);
+        if ui.button("Browse...").clicked()
+            && let Some(picked) = rfd::FileDialog::new()
+                .add_filter("Data", &["csv", "parquet"])
+                .pick_file()
+        {
+            *path = picked.to_string_lossy().to_string();
+        }
+    });
+    if path.is_empty() {
+        return None;
+    }
+    let file = Path::new(path.as_str());
+    let columns = match empirical::numeric_columns(file) {
+        Ok(columns) => columns,
+        Err(e) => {
+            ui.colored_label(ui.visuals().error_fg_color, e.to_string());
+            return None;
+        }
+    };
+    ui.horizontal(|ui| {
+        ui.label("Column");
+        egui::ComboBox::from_id_salt(ui.id().with("data_column"))
+            .selected_text(column.as_str())
+            .show_ui(ui, |ui| {
+                for option in columns {
+                    ui.selectable_value(column, option.clone(), option);
+                }
+            });
+    });
+    if column.is_empty() {
+        return None;
+    }
+    match empirical::load(file, column) {
+        Ok(data) => {
+            ui.label(format!(
+                "{} values from {:.2} to {:.2}, mean {:.2}",
+                data.values.len(),
+                data.values[0],
+                data.values[data.values.len() - 1],
+                data.mean()
+            ));
+            charts::show_data_histogram(ui, ui.id().with("data_histogram"), &data.values);
+            Some(data)
+        }
+        Err(e) => {
+            ui.colored_label(ui.visuals().error_fg_color, e.to_string());
+            None
+        }
+    }
+}
+
+/// The fitted parameters of a distribution, e.g. "mean 20.00, std 2.00".
+pub fn describe(distribution: Distributions, inputs: &DistributionInputs) -> String {
+    match distribution {
+        Distributions::Normal => format!(
+            "mean {:.2}, std {:.2}",
+            inputs.normal_mean, inputs.normal_std
+        ),
+        Distributions::Uniform => format!(
+            "min {:.2}, max {:.2}",
+            inputs.uniform_min, inputs.uniform_max
+        ),
+        Distributions::Triangular => format!(
+            "min {:.2}, mode {:.2}, max {:.2}",
+            inputs.triangular_min, inputs.triangular_mode, inputs.triangular_max
+        ),
+        Distributions::Pert => format!(
+            "min {:.2}, mode {:.2}, max {:.2}",
+            inputs.pert_min, inputs.pert_mode, inputs.pert_max
+        ),
+        Distributions::LogNormal => format!(
+            "log mean {:.3}, log std {:.3}",
+            inputs.lognormal_mu, inputs.lognormal_sigma
+        ),
+        Distributions::Gamma => format!(
+            "shape {:.3}, scale {:.3}",
+            inputs.gamma_shape, inputs.gamma_scale
+        ),
+        Distributions::Beta => format!(
+            "alpha {:.3}, beta {:.3}, min {:.2}, max {:.2}",
+            inputs.beta_alpha, inputs.beta_beta, inputs.beta_min, inputs.beta_max
+        ),
+        Distributions::Weibull => format!(
+            "shape {:.3}, scale {:.3}",
+            inputs.weibull_shape, inputs.weibull_scale
+        ),
+        Distributions::Poisson => format!("lambda {:.3}", inputs.poisson_lambda),
+        Distributions::NegativeBinomial => format!(
+            "r {:.3}, p {:.3}",
+            inputs.negbinomial_r, inputs.negbinomial_p
+        ),
+        Distributions::Binomial => format!("n {}, p {:.3}", inputs.binomial_n, inputs.binomial_p),
+        Distributions::DiscreteUniform => {
+            format!("min {}, max {}", inputs.discrete_min, inputs.discrete_max)
+        }
+        Distributions::Metalog => format!(
+            "through {}",
+            inputs
+                .metalog_points
+                .iter()
+                .map(|point| format!("P{:.0} {:.2}", point.probability * 100.0, point.value))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Distributions::Bernoulli
+        | Distributions::Constant
+        | Distributions::DiscreteTable
+        | Distributions::Empirical => String::new(),
+    }
+}
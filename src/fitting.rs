@@ -0,0 +1,627 @@
+use crate::distributions::{DistributionInputs, Distributions};
+use statrs::distribution::{
+    Beta, Binomial, Continuous, ContinuousCDF, Discrete, DiscreteCDF, DiscreteUniform, Gamma,
+    LogNormal, NegativeBinomial, Normal, Poisson, Triangular, Uniform, Weibull,
+};
+use strum_macros::EnumIter;
+
+/// Keeps the Anderson-Darling logs finite when a fit puts no probability beyond a value.
+const CDF_TOLERANCE: f64 = 1e-12;
+
+/// Bisection steps when solving for the Weibull shape.
+const WEIBULL_BISECTION_STEPS: usize = 100;
+
+/// How fits are ordered, best first. Lower is better for all three.
+#[derive(Debug, PartialEq, Clone, Copy, Default, EnumIter)]
+pub enum FitCriterion {
+    #[default]
+    Aic,
+    KolmogorovSmirnov,
+    AndersonDarling,
+}
+
+impl std::fmt::Display for FitCriterion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FitCriterion::Aic => write!(f, "AIC"),
+            FitCriterion::KolmogorovSmirnov => write!(f, "Kolmogorov-Smirnov"),
+            FitCriterion::AndersonDarling => write!(f, "Anderson-Darling"),
+        }
+    }
+}
+
+/// One distribution fitted to data, with the parameters ready to put into an input.
+#[derive(Debug, Clone)]
+pub struct Fit {
+    pub distribution: Distributions,
+    pub inputs: DistributionInputs,
+    pub log_likelihood: f64,
+    pub aic: f64,
+    /// Largest gap between the fitted and empirical CDFs.
+    pub kolmogorov_smirnov: f64,
+    /// Squared CDF gap weighted towards the tails. Repeated values inflate it for every fit
+    /// alike, so on such data it only ranks fits against each other.
+    pub anderson_darling: f64,
+}
+
+impl Fit {
+    pub fn score(&self, criterion: FitCriterion) -> f64 {
+        match criterion {
+            FitCriterion::Aic => self.aic,
+            FitCriterion::KolmogorovSmirnov => self.kolmogorov_smirnov,
+            FitCriterion::AndersonDarling => self.anderson_darling,
+        }
+    }
+}
+
+/// Orders `fits` from best to worst by `criterion`.
+pub fn rank(fits: &mut [Fit], criterion: FitCriterion) {
+    fits.sort_by(|a, b| a.score(criterion).total_cmp(&b.score(criterion)));
+}
+
+/// Fits every parametric distribution the data allows, ranked by AIC.
+///
+/// Parameters are maximum likelihood estimates where these have a closed form or a simple
+/// one-dimensional solve (Normal, Uniform, LogNormal, Weibull, Poisson, Discrete Uniform), a
+/// close approximation to it for the Gamma, and method of moments otherwise. Bounded
+/// distributions are widened slightly beyond the data so the extremes keep some density.
+/// Positive-only distributions are tried only on positive data and count distributions only
+/// on whole numbers. AIC compares densities with densities and probabilities with
+/// probabilities, so on count data only the count distributions' AICs are comparable with
+/// each other. Constant, Discrete Table and Empirical reproduce the data rather than fit it,
+/// so they are not tried.
+pub fn fit_all(values: &[f64]) -> Vec<Fit> {
+    let mut values: Vec<f64> = values.iter().copied().filter(|x| x.is_finite()).collect();
+    values.sort_by(f64::total_cmp);
+    let Some(data) = Data::new(&values) else {
+        return Vec::new();
+    };
+    let mut fits: Vec<Fit> = candidates(&data)
+        .into_iter()
+        .map(|candidate| candidate.fit(&values))
+        .filter(|fit| fit.aic.is_finite())
+        .collect();
+    rank(&mut fits, FitCriterion::Aic);
+    fits
+}
+
+/// Summary statistics the estimators are built from.
+struct Data {
+    n: f64,
+    mean: f64,
+    /// Maximum likelihood variance, dividing by `n`.
+    variance: f64,
+    min: f64,
+    max: f64,
+    /// Mean and variance of `ln x`, if every value is positive.
+    logs: Option<(f64, f64)>,
+    /// Every value is a whole number.
+    integers: bool,
+    logged: Vec<f64>,
+    values: Vec<f64>,
+}
+
+impl Data {
+    /// None when there are too few distinct values to fit a spread.
+    fn new(values: &[f64]) -> Option<Data> {
+        let (&min, &max) = (values.first()?, values.last()?);
+        if max <= min {
+            return None;
+        }
+        let (mean, variance) = moments(values);
+        let logged: Vec<f64> = if min > 0.0 {
+            values.iter().map(|x| x.ln()).collect()
+        } else {
+            Vec::new()
+        };
+        Some(Data {
+            n: values.len() as f64,
+            mean,
+            variance,
+            min,
+            max,
+            logs: (min > 0.0).then(|| moments(&logged)),
+            integers: values.iter().all(|x| x.fract() == 0.0),
+            logged,
+            values: values.to_vec(),
+        })
+    }
+
+    fn counts(&self) -> bool {
+        self.integers && self.min >= 0.0
+    }
+
+    /// Range of the data widened by one value's share of it on either side.
+    fn widened_range(&self) -> (f64, f64) {
+        let margin = (self.max - self.min) / self.n;
+        (self.min - margin, self.max + margin)
+    }
+}
+
+fn moments(values: &[f64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+    (mean, variance)
+}
+
+/// A fitted distribution before it is scored against the data.
+struct Candidate {
+    distribution: Distributions,
+    inputs: DistributionInputs,
+    parameters: usize,
+    ln_pdf: Box<dyn Fn(f64) -> f64>,
+    cdf: Box<dyn Fn(f64) -> f64>,
+    /// `P(X < x)`, which differs from the CDF at the values of a discrete distribution.
+    cdf_below: Box<dyn Fn(f64) -> f64>,
+}
+
+impl Candidate {
+    fn continuous<D>(
+        distribution: Distributions,
+        inputs: DistributionInputs,
+        parameters: usize,
+        fitted: D,
+    ) -> Candidate
+    where
+        D: Continuous<f64, f64> + ContinuousCDF<f64, f64> + Clone + 'static,
+    {
+        let (density, cumulative, below) = (fitted.clone(), fitted.clone(), fitted);
+        Candidate {
+            distribution,
+            inputs,
+            parameters,
+            ln_pdf: Box::new(move |x| density.ln_pdf(x)),
+            cdf: Box::new(move |x| cumulative.cdf(x)),
+            cdf_below: Box::new(move |x| below.cdf(x)),
+        }
+    }
+
+    /// A distribution over counts, which has no probability off the whole numbers.
+    fn counts<D>(
+        distribution: Distributions,
+        inputs: DistributionInputs,
+        parameters: usize,
+        fitted: D,
+    ) -> Candidate
+    where
+        D: Discrete<u64, f64> + DiscreteCDF<u64, f64> + Clone + 'static,
+    {
+        let (mass, cumulative, below) = (fitted.clone(), fitted.clone(), fitted);
+        Candidate {
+            distribution,
+            inputs,
+            parameters,
+            ln_pdf: Box::new(move |x| {
+                if x >= 0.0 && x.fract() == 0.0 {
+                    mass.ln_pmf(x as u64)
+                } else {
+                    f64::NEG_INFINITY
+                }
+            }),
+            cdf: Box::new(move |x| {
+                if x < 0.0 {
+                    0.0
+                } else {
+                    cumulative.cdf(x as u64)
+                }
+            }),
+            cdf_below: Box::new(move |x| {
+                if x < 1.0 {
+                    0.0
+                } else {
+                    below.cdf(x.ceil() as u64 - 1)
+                }
+            }),
+        }
+    }
+
+    fn fit(self, sorted: &[f64]) -> Fit {
+        let n = sorted.len() as f64;
+        let log_likelihood: f64 = sorted.iter().map(|&x| (self.ln_pdf)(x)).sum();
+        let mut kolmogorov_smirnov: f64 = 0.0;
+        let mut anderson_darling = -n;
+        // At a discrete distribution's values the CDF jumps, so Anderson-Darling takes the middle
+        // of each jump. That is just the CDF for continuous distributions.
+        let middle = |x: f64| {
+            let p = 0.5 * ((self.cdf_below)(x) + (self.cdf)(x));
+            p.clamp(CDF_TOLERANCE, 1.0 - CDF_TOLERANCE)
+        };
+        for (i, &x) in sorted.iter().enumerate() {
+            kolmogorov_smirnov = kolmogorov_smirnov
+                .max((i + 1) as f64 / n - (self.cdf)(x))
+                .max((self.cdf_below)(x) - i as f64 / n);
+            let upper = middle(sorted[sorted.len() - 1 - i]);
+            anderson_darling -= (2 * i + 1) as f64 / n * (middle(x).ln() + (1.0 - upper).ln());
+        }
+        Fit {
+            distribution: self.distribution,
+            inputs: self.inputs,
+            log_likelihood,
+            aic: 2.0 * self.parameters as f64 - 2.0 * log_likelihood,
+            kolmogorov_smirnov,
+            anderson_darling,
+        }
+    }
+}
+
+fn candidates(data: &Data) -> Vec<Candidate> {
+    let mut candidates = Vec::new();
+    let std = data.variance.sqrt();
+
+    if let Ok(normal) = Normal::new(data.mean, std) {
+        let inputs = DistributionInputs {
+            normal_mean: data.mean,
+            normal_std: std,
+            ..Default::default()
+        };
+        candidates.push(Candidate::continuous(
+            Distributions::Normal,
+            inputs,
+            2,
+            normal,
+        ));
+    }
+
+    if let Ok(uniform) = Uniform::new(data.min, data.max) {
+        let inputs = DistributionInputs {
+            uniform_min: data.min,
+            uniform_max: data.max,
+            ..Default::default()
+        };
+        candidates.push(Candidate::continuous(
+            Distributions::Uniform,
+            inputs,
+            2,
+            uniform,
+        ));
+    }
+
+    let (min, max) = data.widened_range();
+    let mode = (3.0 * data.mean - min - max).clamp(min, max);
+    if let Ok(triangular) = Triangular::new(min, max, mode) {
+        let inputs = DistributionInputs {
+            triangular_min: min,
+            triangular_mode: mode,
+            triangular_max: max,
+            ..Default::default()
+        };
+        candidates.push(Candidate::continuous(
+            Distributions::Triangular,
+            inputs,
+            3,
+            triangular,
+        ));
+    }
+
+    // A PERT is a Beta whose shapes follow from where the mode sits in the range.
+    let mode = ((6.0 * data.mean - min - max) / 4.0).clamp(min, max);
+    let range = max - min;
+    if let Ok(beta) = Beta::new(
+        1.0 + 4.0 * (mode - min) / range,
+        1.0 + 4.0 * (max - mode) / range,
+    ) {
+        let inputs = DistributionInputs {
+            pert_min: min,
+            pert_mode: mode,
+            pert_max: max,
+            ..Default::default()
+        };
+        candidates.push(scaled_beta(
+            Distributions::Pert,
+            inputs,
+            3,
+            beta,
+            min,
+            range,
+        ));
+    }
+
+    let (mean, variance) = ((data.mean - min) / range, data.variance / range.powi(2));
+    let common = mean * (1.0 - mean) / variance - 1.0;
+    if let Ok(beta) = Beta::new(mean * common, (1.0 - mean) * common) {
+        let inputs = DistributionInputs {
+            beta_alpha: mean * common,
+            beta_beta: (1.0 - mean) * common,
+            beta_min: min,
+            beta_max: max,
+            ..Default::default()
+        };
+        candidates.push(scaled_beta(
+            Distributions::Beta,
+            inputs,
+            4,
+            beta,
+            min,
+            range,
+        ));
+    }
+
+    if let Some((log_mean, log_variance)) = data.logs {
+        let log_std = log_variance.sqrt();
+        if let Ok(lognormal) = LogNormal::new(log_mean, log_std) {
+            let inputs = DistributionInputs {
+                lognormal_mu: log_mean,
+                lognormal_sigma: log_std,
+                ..Default::default()
+            };
+            candidates.push(Candidate::continuous(
+                Distributions::LogNormal,
+                inputs,
+                2,
+                lognormal,
+            ));
+        }
+
+        // Minka's closed-form approximation to the maximum likelihood shape.
+        let s = data.mean.ln() - log_mean;
+        let shape = (3.0 - s + ((s - 3.0).powi(2) + 24.0 * s).sqrt()) / (12.0 * s);
+        let scale = data.mean / shape;
+        if let Ok(gamma) = Gamma::new(shape, 1.0 / scale) {
+            let inputs = DistributionInputs {
+                gamma_shape: shape,
+                gamma_scale: scale,
+                ..Default::default()
+            };
+            candidates.push(Candidate::continuous(
+                Distributions::Gamma,
+                inputs,
+                2,
+                gamma,
+            ));
+        }
+
+        let (shape, scale) = weibull_estimate(data);
+        if let Ok(weibull) = Weibull::new(shape, scale) {
+            let inputs = DistributionInputs {
+                weibull_shape: shape,
+                weibull_scale: scale,
+                ..Default::default()
+            };
+            candidates.push(Candidate::continuous(
+                Distributions::Weibull,
+                inputs,
+                2,
+                weibull,
+            ));
+        }
+    }
+
+    if data.counts() {
+        if let Ok(poisson) = Poisson::new(data.mean) {
+            let inputs = DistributionInputs {
+                poisson_lambda: data.mean,
+                ..Default::default()
+            };
+            candidates.push(Candidate::counts(
+                Distributions::Poisson,
+                inputs,
+                1,
+                poisson,
+            ));
+        }
+
+        // Only overdispersed counts have a Negative Binomial, and only underdispersed a Binomial.
+        if data.variance > data.mean {
+            let p = data.mean / data.variance;
+            let r = data.mean.powi(2) / (data.variance - data.mean);
+            if let Ok(negative_binomial) = NegativeBinomial::new(r, p) {
+                let inputs = DistributionInputs {
+                    negbinomial_r: r,
+                    negbinomial_p: p,
+                    ..Default::default()
+                };
+                candidates.push(Candidate::counts(
+                    Distributions::NegativeBinomial,
+                    inputs,
+                    2,
+                    negative_binomial,
+                ));
+            }
+        }
+        if data.variance < data.mean {
+            let trials = ((data.mean / (1.0 - data.variance / data.mean)).round()).max(data.max);
+            let p = data.mean / trials;
+            if let Ok(binomial) = Binomial::new(p, trials as u64) {
+                let inputs = DistributionInputs {
+                    binomial_n: trials as u64,
+                    binomial_p: p,
+                    ..Default::default()
+                };
+                candidates.push(Candidate::counts(
+                    Distributions::Binomial,
+                    inputs,
+                    2,
+                    binomial,
+                ));
+            }
+        }
+    }
+
+    if data.integers {
+        let (low, high) = (data.min as i64, data.max as i64);
+        if let Ok(uniform) = DiscreteUniform::new(low, high) {
+            let inputs = DistributionInputs {
+                discrete_min: low,
+                discrete_max: high,
+                ..Default::default()
+            };
+            let (mass, cumulative, below) = (uniform, uniform, uniform);
+            candidates.push(Candidate {
+                distribution: Distributions::DiscreteUniform,
+                inputs,
+                parameters: 2,
+                ln_pdf: Box::new(move |x| {
+                    if x.fract() == 0.0 {
+                        mass.ln_pmf(x as i64)
+                    } else {
+                        f64::NEG_INFINITY
+                    }
+                }),
+                cdf: Box::new(move |x| cumulative.cdf(x.floor() as i64)),
+                cdf_below: Box::new(move |x| below.cdf(x.ceil() as i64 - 1)),
+            });
+        }
+    }
+
+    candidates
+}
+
+/// A Beta on `[0, 1]` stretched over `[min, min + range]`.
+fn scaled_beta(
+    distribution: Distributions,
+    inputs: DistributionInputs,
+    parameters: usize,
+    beta: Beta,
+    min: f64,
+    range: f64,
+) -> Candidate {
+    let unit = move |x: f64| ((x - min) / range).clamp(0.0, 1.0);
+    let (density, cumulative, below) = (beta, beta, beta);
+    Candidate {
+        distribution,
+        inputs,
+        parameters,
+        ln_pdf: Box::new(move |x| density.ln_pdf((x - min) / range) - range.ln()),
+        cdf: Box::new(move |x| cumulative.cdf(unit(x))),
+        cdf_below: Box::new(move |x| below.cdf(unit(x))),
+    }
+}
+
+/// Maximum likelihood Weibull `(shape, scale)` of positive data.
+///
+/// The shape solves `Σ xᵏ ln x / Σ xᵏ - 1/k = mean(ln x)`, whose left side rises with `k`, so
+/// it is bisected over a log scale. Values are divided by the largest first so `xᵏ` cannot
+/// overflow; the equation is unchanged by that.
+fn weibull_estimate(data: &Data) -> (f64, f64) {
+    let scaled: Vec<(f64, f64)> = data
+        .values
+        .iter()
+        .zip(&data.logged)
+        .map(|(x, ln_x)| (x / data.max, ln_x - data.max.ln()))
+        .collect();
+    let mean_log = scaled.iter().map(|(_, ln_y)| ln_y).sum::<f64>() / data.n;
+    let excess = |shape: f64| {
+        let (weighted, total) = scaled
+            .iter()
+            .fold((0.0, 0.0), |(weighted, total), (y, ln_y)| {
+                let power = y.powf(shape);
+                (weighted + power * ln_y, total + power)
+            });
+        weighted / total - 1.0 / shape - mean_log
+    };
+    let (mut low, mut high) = (0.01_f64.ln(), 100.0_f64.ln());
+    for _ in 0..WEIBULL_BISECTION_STEPS {
+        let mid = 0.5 * (low + high);
+        if excess(mid.exp()) < 0.0 {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    let shape = (0.5 * (low + high)).exp();
+    let mean_power = scaled.iter().map(|(y, _)| y.powf(shape)).sum::<f64>() / data.n;
+    (shape, data.max * mean_power.powf(1.0 / shape))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray_rand::rand::{Rng, SeedableRng};
+    use ndarray_rand::rand_distr::{
+        Distribution, Gamma as GammaSampler, Normal as NormalSampler, Poisson as PoissonSampler,
+        Weibull as WeibullSampler,
+    };
+    use rand_chacha::ChaCha12Rng;
+    use strum::IntoEnumIterator;
+
+    const SAMPLE_SIZE: usize = 5000;
+
+    fn sample(distribution: impl Distribution<f64>) -> Vec<f64> {
+        ChaCha12Rng::seed_from_u64(7)
+            .sample_iter(distribution)
+            .take(SAMPLE_SIZE)
+            .collect()
+    }
+
+    /// Fits `values`, checks `truth` ranks first by every criterion, and returns its fit.
+    fn best_fit(values: &[f64], truth: Distributions) -> DistributionInputs {
+        best_fit_among(values, truth, &[truth])
+    }
+
+    /// Like [`best_fit`], but lets the criteria that do not charge for parameters rank any of
+    /// `nesting` first, as those families contain `truth` as a limit.
+    fn best_fit_among(
+        values: &[f64],
+        truth: Distributions,
+        nesting: &[Distributions],
+    ) -> DistributionInputs {
+        let mut fits = fit_all(values);
+        for criterion in FitCriterion::iter() {
+            rank(&mut fits, criterion);
+            let first = fits[0].distribution;
+            match criterion {
+                FitCriterion::Aic => assert_eq!(first, truth, "ranked first by {criterion}"),
+                _ => assert!(
+                    nesting.contains(&first),
+                    "{first} ranked first by {criterion}"
+                ),
+            }
+        }
+        rank(&mut fits, FitCriterion::Aic);
+        fits.swap_remove(0).inputs
+    }
+
+    fn assert_close(name: &str, estimate: f64, truth: f64, tolerance: f64) {
+        assert!(
+            (estimate - truth).abs() <= tolerance * truth.abs(),
+            "{name} is {estimate}, expected {truth}"
+        );
+    }
+
+    #[test]
+    fn normal_sample_fits_normal() {
+        let fit = best_fit(
+            &sample(NormalSampler::new(-10.0, 4.0).unwrap()),
+            Distributions::Normal,
+        );
+        assert_close("mean", fit.normal_mean, -10.0, 0.02);
+        assert_close("std", fit.normal_std, 4.0, 0.05);
+    }
+
+    #[test]
+    fn gamma_sample_fits_gamma() {
+        let fit = best_fit(
+            &sample(GammaSampler::new(2.0, 3.0).unwrap()),
+            Distributions::Gamma,
+        );
+        assert_close("shape", fit.gamma_shape, 2.0, 0.1);
+        assert_close("scale", fit.gamma_scale, 3.0, 0.1);
+    }
+
+    #[test]
+    fn weibull_sample_fits_weibull() {
+        let fit = best_fit(
+            &sample(WeibullSampler::new(10.0, 3.5).unwrap()),
+            Distributions::Weibull,
+        );
+        assert_close("shape", fit.weibull_shape, 3.5, 0.05);
+        assert_close("scale", fit.weibull_scale, 10.0, 0.02);
+    }
+
+    #[test]
+    fn poisson_sample_fits_poisson() {
+        // A Binomial with many trials or a Negative Binomial with a large r is all but a
+        // Poisson, and may match a sample's CDF a little more closely by chance.
+        let fit = best_fit_among(
+            &sample(PoissonSampler::new(4.0).unwrap()),
+            Distributions::Poisson,
+            &[
+                Distributions::Poisson,
+                Distributions::Binomial,
+                Distributions::NegativeBinomial,
+            ],
+        );
+        assert_close("lambda", fit.poisson_lambda, 4.0, 0.05);
+    }
+}
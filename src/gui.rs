@@ -1,20 +1,40 @@
-use crate::distributions::{DistributionInputs, Distributions};
-use crate::mcs::start_simulation;
-use crate::message::SimulationMessage;
+use crate::charts::{self, ResultCharts};
+use crate::fit_panel::{self, DistributionFitter};
+use crate::quantile_panel::QuantileEntry;
 use eframe::egui;
+use ndarray_rand::rand;
+use sandhiya::config::SimulationConfig;
+use sandhiya::convergence::{ConvergenceTarget, PrecisionMeasure, TargetStatistic};
+use sandhiya::correlation::{CorrelatedInput, CorrelationMatrix};
+use sandhiya::distributions::{DistributionInputs, Distributions, MetalogPoint, TableRow};
+use sandhiya::errors::InvalidField;
+use sandhiya::mcs::{DEFAULT_OUTPUT_PATH, start_simulation};
+use sandhiya::message::{CancellationToken, SimulationMessage};
+use sandhiya::metalog::Metalog;
+use sandhiya::quantiles;
+use sandhiya::sampling::{SamplingStrategy, StrategyComparison, compare_strategies};
+use sandhiya::scenario::{load_scenario, save_scenario};
+use sandhiya::sensitivity::{
+    DEFAULT_SENSITIVITY_PATH, SENSITIVITY_METRIC, SensitivityAnalysis, run_sensitivity,
+};
+use sandhiya::sobol::{DEFAULT_SOBOL_PATH, SobolAnalysis, run_sobol};
+use sandhiya::summary::SimulationSummary;
+use sandhiya::validation::Field;
 use std::{
-    collections::HashMap,
-    sync::mpsc::{self, Receiver},
+    path::Path,
+    sync::mpsc::{self, Receiver, Sender},
     thread,
 };
-use strum::IntoEnumIterator; 
+use strum::IntoEnumIterator;
 
 #[derive(Default)]
 pub struct MyEguiApp {
     commission: f64,
-    number_of_months: f64,
-    number_of_trials: f64,
+    number_of_months: usize,
+    number_of_trials: usize,
     transport_bonus: f64,
+    income_floor: f64,
+    seed: u64,
     error_message: String,
     show_error_popup: bool,
 
@@ -33,12 +53,30 @@ pub struct MyEguiApp {
     conversion_rate_distr: Distributions,
     conversion_rate_inputs: DistributionInputs,
 
-    data: HashMap<String, (Distributions, DistributionInputs)>,
+    correlate_inputs: bool,
+    correlation: CorrelationMatrix,
+
+    stop_on_convergence: bool,
+    convergence: ConvergenceTarget,
+
+    sampling: SamplingStrategy,
+    antithetic: bool,
+    control_variate: bool,
+
+    config: SimulationConfig,
+    /// Every requirement `config` breaks, plus the fields the last run with it failed on.
+    /// Refreshed only when the editors change, as validating reads Empirical files and refits
+    /// Metalogs.
+    problems: Vec<InvalidField>,
     probability_distributions: Vec<Distributions>,
     is_simulating: bool,
     progress: f32,
     simulation_receiver: Option<Receiver<SimulationMessage>>,
     simulation_result: Option<SimulationMessage>,
+    charts: Option<ResultCharts>,
+    fitter: Option<DistributionFitter>,
+    quantile_entry: Option<QuantileEntry>,
+    cancellation: CancellationToken,
 }
 
 impl MyEguiApp {
@@ -46,145 +84,164 @@ impl MyEguiApp {
         let mut style = (*cc.egui_ctx.style()).clone();
         style.animation_time = 0.0;
         cc.egui_ctx.set_style(style);
-        Self {
-            number_of_months: 12.0,
-            number_of_trials: 1000.0,
+        let mut app = Self {
             probability_distributions: Distributions::iter()
                 .filter(|&dist| dist != Distributions::Bernoulli)
                 .collect(),
             ..Default::default()
-        }
+        };
+        app.apply_config(SimulationConfig::default());
+        app.revalidate(app.build_config());
+        app
     }
 
     /// Gathers and validates all user inputs.
-    /// This is now called only when the "Start Simulation" button is clicked.
+    /// Called by [`Self::launch`] before any run starts, whichever button launched it.
     fn gather_and_validate_values(&mut self) -> Result<(), String> {
-        // --- Validation Logic ---
-        // This helper function reduces code duplication for validation.
-        let validate =
-            |dist: &Distributions, inputs: &DistributionInputs, name: &str| -> Result<(), String> {
-                match dist {
-                    Distributions::Uniform => {
-                        if inputs.uniform_min > inputs.uniform_max {
-                            return Err(format!("For {}, ensure min <= max.", name));
-                        }
-                    }
-                    Distributions::Triangular => {
-                        if !(inputs.triangular_min <= inputs.triangular_mode
-                            && inputs.triangular_mode <= inputs.triangular_max)
-                        {
-                            return Err(format!("For {}, ensure min <= mode <= max.", name));
-                        }
-                    }
-                    Distributions::Pert => {
-                        if !(inputs.pert_min <= inputs.pert_mode
-                            && inputs.pert_mode <= inputs.pert_max)
-                        {
-                            return Err(format!("For {}, ensure min <= mode <= max.", name));
-                        }
-                    }
-                    _ => {} // Other distributions have no logical constraints here.
-                }
-                Ok(())
-            };
+        let config = self.build_config();
+        config.validate().map_err(|e| e.to_string())?;
+        self.config = config;
+        Ok(())
+    }
 
-        // Validate all inputs that have logical constraints.
-        validate(&self.price_distr, &self.price_inputs, "Price")?;
-        validate(
-            &self.retailers_day_distr,
-            &self.retailers_day_inputs,
-            "Retailers/Day",
-        )?;
-        validate(
-            &self.workdays_month_distr,
-            &self.workdays_month_inputs,
-            "Workdays/Month",
-        )?;
-        validate(
-            &self.units_sale_distr,
-            &self.units_sale_inputs,
-            "Units/Sale",
-        )?;
-        validate(
-            &self.conversion_rate_distr,
-            &self.conversion_rate_inputs,
-            "Conversion Rate",
-        )?;
-
-        // --- Data Gathering ---
-        let mut hm: HashMap<String, (Distributions, DistributionInputs)> = HashMap::new();
-        hm.insert(
-            "Prices".into(),
-            (self.price_distr, self.price_inputs.clone()),
-        );
-        hm.insert(
-            "Retailers_per_Day".into(),
-            (self.retailers_day_distr, self.retailers_day_inputs.clone()),
-        );
-        hm.insert(
-            "Workdays_per_Month".into(),
-            (
+    /// Makes `config` the current one and lists the requirements it breaks.
+    fn revalidate(&mut self, config: SimulationConfig) {
+        self.problems = match config.validate() {
+            Ok(()) => Vec::new(),
+            Err(e) => e.0,
+        };
+        self.config = config;
+    }
+
+    /// Collects the current editor state into a [`SimulationConfig`] without validating it.
+    fn build_config(&self) -> SimulationConfig {
+        SimulationConfig {
+            prices: (self.price_distr, self.price_inputs.clone()),
+            retailers_per_day: (self.retailers_day_distr, self.retailers_day_inputs.clone()),
+            workdays_per_month: (
                 self.workdays_month_distr,
                 self.workdays_month_inputs.clone(),
             ),
-        );
-        hm.insert(
-            "Conversion_Rate".into(),
-            (
+            units: (self.units_sale_distr, self.units_sale_inputs.clone()),
+            conversion_rate: (
                 self.conversion_rate_distr,
                 self.conversion_rate_inputs.clone(),
             ),
-        );
-        hm.insert(
-            "Units".into(),
-            (self.units_sale_distr, self.units_sale_inputs.clone()),
-        );
+            commission_rate: self.commission,
+            transport_bonus: self.transport_bonus,
+            income_floor: self.income_floor,
+            num_months: self.number_of_months,
+            trials: self.number_of_trials,
+            convergence: self
+                .stop_on_convergence
+                .then(|| self.convergence.clone()),
+            correlation: if self.correlate_inputs {
+                self.correlation.clone()
+            } else {
+                CorrelationMatrix::default()
+            },
+            sampling: self.sampling,
+            antithetic: self.antithetic,
+            control_variate: self.control_variate,
+            seed: self.seed,
+        }
+    }
 
-        let commission_inputs = DistributionInputs {
-            constant_val: self.commission,
-            ..Default::default()
-        };
-        let transport_bonus_inputs = DistributionInputs {
-            constant_val: self.transport_bonus,
-            ..Default::default()
-        };
-        let trials_inputs = DistributionInputs {
-            constant_val: self.number_of_trials,
-            ..Default::default()
-        };
-        let months_inputs = DistributionInputs {
-            constant_val: self.number_of_months,
-            ..Default::default()
+    /// Loads `config` into the editors, the inverse of [`Self::build_config`].
+    fn apply_config(&mut self, config: SimulationConfig) {
+        (self.price_distr, self.price_inputs) = config.prices;
+        (self.retailers_day_distr, self.retailers_day_inputs) = config.retailers_per_day;
+        (self.workdays_month_distr, self.workdays_month_inputs) = config.workdays_per_month;
+        (self.units_sale_distr, self.units_sale_inputs) = config.units;
+        (self.conversion_rate_distr, self.conversion_rate_inputs) = config.conversion_rate;
+        self.commission = config.commission_rate;
+        self.transport_bonus = config.transport_bonus;
+        self.income_floor = config.income_floor;
+        self.number_of_months = config.num_months;
+        self.number_of_trials = config.trials;
+        self.stop_on_convergence = config.convergence.is_some();
+        self.convergence = config.convergence.unwrap_or_default();
+        self.correlate_inputs = !config.correlation.is_identity();
+        self.correlation = config.correlation;
+        self.sampling = config.sampling;
+        self.antithetic = config.antithetic;
+        self.control_variate = config.control_variate;
+        self.seed = config.seed;
+    }
+
+    /// Asks for a scenario file and loads it into the editors.
+    fn open_scenario(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Scenario", &["toml", "json"])
+            .pick_file()
+        else {
+            return;
         };
+        match load_scenario(&path) {
+            Ok(config) => self.apply_config(config),
+            Err(e) => {
+                self.error_message = e.to_string();
+                self.show_error_popup = true;
+            }
+        }
+    }
 
-        hm.insert(
-            "Commission_Rate".into(),
-            (Distributions::Constant, commission_inputs),
-        );
-        hm.insert(
-            "Transport_Bonus".into(),
-            (Distributions::Constant, transport_bonus_inputs),
-        );
-        hm.insert("Trials".into(), (Distributions::Constant, trials_inputs));
-        hm.insert(
-            "Number_of_Months".into(),
-            (Distributions::Constant, months_inputs),
-        );
+    /// Asks where to save and writes the current editor state as a scenario file.
+    fn save_scenario(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Scenario", &["toml", "json"])
+            .set_file_name("scenario.toml")
+            .save_file()
+        else {
+            return;
+        };
+        if let Err(e) = save_scenario(&path, &self.build_config()) {
+            self.error_message = e.to_string();
+            self.show_error_popup = true;
+        }
+    }
 
-        self.data = hm;
-        Ok(())
+    /// Validates the editors and runs `job` on a worker thread. The job reports its
+    /// progress and outcome through the channel.
+    fn launch(
+        &mut self,
+        job: fn(&SimulationConfig, Sender<SimulationMessage>, &CancellationToken),
+    ) {
+        match self.gather_and_validate_values() {
+            Ok(()) => {
+                self.is_simulating = true;
+                self.simulation_result = None;
+                self.charts = None;
+                self.progress = 0.0;
+                let (sender, receiver) = mpsc::channel::<SimulationMessage>();
+                self.simulation_receiver = Some(receiver);
+                let config = self.config.clone();
+                self.cancellation = CancellationToken::default();
+                let cancellation = self.cancellation.clone();
+                thread::spawn(move || job(&config, sender, &cancellation));
+            }
+            Err(err) => {
+                self.error_message = err;
+                self.show_error_popup = true;
+            }
+        }
     }
 
-    /// Renders the UI for selecting a distribution and its parameters.
+    /// Renders the UI for selecting a distribution and its parameters, marking those that
+    /// break one of `problems`.
     fn input_distributions(
         ui: &mut egui::Ui,
         distribution: Distributions,
         inputs: &mut DistributionInputs,
+        problems: &[InvalidField],
     ) {
         match distribution {
             Distributions::Bernoulli => {
                 ui.label("Probability");
-                ui.add(
+                Self::add_parameter(
+                    ui,
+                    problems,
+                    "bernoulli_prob",
                     egui::DragValue::new(&mut inputs.bernoulli_prob)
                         .speed(0.01)
                         .range(0.0..=1.0),
@@ -192,9 +249,17 @@ impl MyEguiApp {
             }
             Distributions::Normal => {
                 ui.label("Mean");
-                ui.add(egui::DragValue::new(&mut inputs.normal_mean).speed(0.1));
+                Self::add_parameter(
+                    ui,
+                    problems,
+                    "normal_mean",
+                    egui::DragValue::new(&mut inputs.normal_mean).speed(0.1),
+                );
                 ui.label("Standard Deviation");
-                ui.add(
+                Self::add_parameter(
+                    ui,
+                    problems,
+                    "normal_std",
                     egui::DragValue::new(&mut inputs.normal_std)
                         .speed(0.1)
                         .range(0.0..=f64::INFINITY),
@@ -202,174 +267,1125 @@ impl MyEguiApp {
             }
             Distributions::Uniform => {
                 ui.label("Min");
-                ui.add(egui::DragValue::new(&mut inputs.uniform_min).speed(0.1));
+                Self::add_parameter(
+                    ui,
+                    problems,
+                    "uniform_min",
+                    egui::DragValue::new(&mut inputs.uniform_min).speed(0.1),
+                );
                 ui.label("Max");
-                ui.add(egui::DragValue::new(&mut inputs.uniform_max).speed(0.1));
+                Self::add_parameter(
+                    ui,
+                    problems,
+                    "uniform_max",
+                    egui::DragValue::new(&mut inputs.uniform_max).speed(0.1),
+                );
             }
             Distributions::Constant => {
                 ui.label("Value");
-                ui.add(egui::DragValue::new(&mut inputs.constant_val).speed(0.1));
+                Self::add_parameter(
+                    ui,
+                    problems,
+                    "constant_val",
+                    egui::DragValue::new(&mut inputs.constant_val).speed(0.1),
+                );
             }
             Distributions::Triangular => {
                 ui.label("Min");
-                ui.add(egui::DragValue::new(&mut inputs.triangular_min).speed(0.1));
+                Self::add_parameter(
+                    ui,
+                    problems,
+                    "triangular_min",
+                    egui::DragValue::new(&mut inputs.triangular_min).speed(0.1),
+                );
                 ui.label("Mode");
-                ui.add(egui::DragValue::new(&mut inputs.triangular_mode).speed(0.1));
+                Self::add_parameter(
+                    ui,
+                    problems,
+                    "triangular_mode",
+                    egui::DragValue::new(&mut inputs.triangular_mode).speed(0.1),
+                );
                 ui.label("Max");
-                ui.add(egui::DragValue::new(&mut inputs.triangular_max).speed(0.1));
+                Self::add_parameter(
+                    ui,
+                    problems,
+                    "triangular_max",
+                    egui::DragValue::new(&mut inputs.triangular_max).speed(0.1),
+                );
             }
             Distributions::Pert => {
                 ui.label("Min");
-                ui.add(egui::DragValue::new(&mut inputs.pert_min).speed(0.1));
+                Self::add_parameter(
+                    ui,
+                    problems,
+                    "pert_min",
+                    egui::DragValue::new(&mut inputs.pert_min).speed(0.1),
+                );
                 ui.label("Mode");
-                ui.add(egui::DragValue::new(&mut inputs.pert_mode).speed(0.1));
+                Self::add_parameter(
+                    ui,
+                    problems,
+                    "pert_mode",
+                    egui::DragValue::new(&mut inputs.pert_mode).speed(0.1),
+                );
                 ui.label("Max");
-                ui.add(egui::DragValue::new(&mut inputs.pert_max).speed(0.1));
+                Self::add_parameter(
+                    ui,
+                    problems,
+                    "pert_max",
+                    egui::DragValue::new(&mut inputs.pert_max).speed(0.1),
+                );
             }
-        }
-    }
-
-    /// A helper function to create a row for a distribution selector.
-    fn show_distribution_controls(
-        ui: &mut egui::Ui,
-        label_text: &str,
-        distribution: &mut Distributions,
-        inputs: &mut DistributionInputs,
-        options: &Vec<Distributions>,
-    ) {
-        ui.horizontal(|ui| {
-            ui.label(label_text);
-            egui::ComboBox::from_label(format!("Select a distribution for {}", label_text))
-                .selected_text(distribution.to_string())
-                .show_ui(ui, |ui| {
-                    for option in options {
-                        ui.selectable_value(distribution, *option, option.to_string());
-                    }
-                });
-            Self::input_distributions(ui, *distribution, inputs);
-        });
-    }
-}
-
-impl eframe::App for MyEguiApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading("Monte Carlo Simulation");
-            ui.add_space(10.0);
-
-            // --- DRAWING PHASE ---
-            // The UI is drawn here. No validation or logic is performed in this phase.
-            ui.add_enabled_ui(!self.is_simulating, |ui| {
-                ui.horizontal(|ui| {
-                    ui.label("Commission rate");
-                    ui.add(egui::DragValue::new(&mut self.commission).range(0.0..=100.0));
-                    ui.label("%");
-                });
-                ui.horizontal(|ui| {
-                    ui.label("Transport Bonus");
-                    ui.add(egui::DragValue::new(&mut self.transport_bonus).range(0.0..=100.0));
-                    ui.label("%");
-                });
-
-                Self::show_distribution_controls(
+            Distributions::LogNormal => {
+                ui.label("Log Mean (μ)");
+                Self::add_parameter(
+                    ui,
+                    problems,
+                    "lognormal_mu",
+                    egui::DragValue::new(&mut inputs.lognormal_mu).speed(0.01),
+                );
+                ui.label("Log Std (σ)");
+                Self::add_parameter(
+                    ui,
+                    problems,
+                    "lognormal_sigma",
+                    egui::DragValue::new(&mut inputs.lognormal_sigma)
+                        .speed(0.01)
+                        .range(0.0..=f64::INFINITY),
+                );
+            }
+            Distributions::Gamma => {
+                ui.label("Shape");
+                Self::add_parameter(
+                    ui,
+                    problems,
+                    "gamma_shape",
+                    egui::DragValue::new(&mut inputs.gamma_shape)
+                        .speed(0.1)
+                        .range(0.0..=f64::INFINITY),
+                );
+                ui.label("Scale");
+                Self::add_parameter(
                     ui,
-                    "Price to retailer",
-                    &mut self.price_distr,
-                    &mut self.price_inputs,
-                    &self.probability_distributions,
+                    problems,
+                    "gamma_scale",
+                    egui::DragValue::new(&mut inputs.gamma_scale)
+                        .speed(0.1)
+                        .range(0.0..=f64::INFINITY),
+                );
+            }
+            Distributions::Beta => {
+                ui.label("Alpha");
+                Self::add_parameter(
+                    ui,
+                    problems,
+                    "beta_alpha",
+                    egui::DragValue::new(&mut inputs.beta_alpha)
+                        .speed(0.1)
+                        .range(0.0..=f64::INFINITY),
+                );
+                ui.label("Beta");
+                Self::add_parameter(
+                    ui,
+                    problems,
+                    "beta_beta",
+                    egui::DragValue::new(&mut inputs.beta_beta)
+                        .speed(0.1)
+                        .range(0.0..=f64::INFINITY),
                 );
-                Self::show_distribution_controls(
+                ui.label("Min");
+                Self::add_parameter(
                     ui,
-                    "Retailers/Day",
-                    &mut self.retailers_day_distr,
-                    &mut self.retailers_day_inputs,
-                    &self.probability_distributions,
+                    problems,
+                    "beta_min",
+                    egui::DragValue::new(&mut inputs.beta_min).speed(0.1),
                 );
-                Self::show_distribution_controls(
+                ui.label("Max");
+                Self::add_parameter(
                     ui,
-                    "Workdays/Month",
-                    &mut self.workdays_month_distr,
-                    &mut self.workdays_month_inputs,
-                    &self.probability_distributions,
+                    problems,
+                    "beta_max",
+                    egui::DragValue::new(&mut inputs.beta_max).speed(0.1),
                 );
-                Self::show_distribution_controls(
+            }
+            Distributions::Weibull => {
+                ui.label("Shape");
+                Self::add_parameter(
                     ui,
-                    "Units/Sale",
-                    &mut self.units_sale_distr,
-                    &mut self.units_sale_inputs,
-                    &self.probability_distributions,
+                    problems,
+                    "weibull_shape",
+                    egui::DragValue::new(&mut inputs.weibull_shape)
+                        .speed(0.1)
+                        .range(0.0..=f64::INFINITY),
                 );
-                Self::show_distribution_controls(
+                ui.label("Scale");
+                Self::add_parameter(
                     ui,
-                    "Conversion Rate",
-                    &mut self.conversion_rate_distr,
-                    &mut self.conversion_rate_inputs,
-                    &self.probability_distributions,
+                    problems,
+                    "weibull_scale",
+                    egui::DragValue::new(&mut inputs.weibull_scale)
+                        .speed(0.1)
+                        .range(0.0..=f64::INFINITY),
                 );
+            }
+            Distributions::Poisson => {
+                ui.label("Lambda");
+                Self::add_parameter(
+                    ui,
+                    problems,
+                    "poisson_lambda",
+                    egui::DragValue::new(&mut inputs.poisson_lambda)
+                        .speed(0.1)
+                        .range(0.0..=f64::INFINITY),
+                );
+            }
+            Distributions::NegativeBinomial => {
+                ui.label("Successes (r)");
+                Self::add_parameter(
+                    ui,
+                    problems,
+                    "negbinomial_r",
+                    egui::DragValue::new(&mut inputs.negbinomial_r)
+                        .speed(0.1)
+                        .range(0.0..=f64::INFINITY),
+                );
+                ui.label("Success Probability (p)");
+                Self::add_parameter(
+                    ui,
+                    problems,
+                    "negbinomial_p",
+                    egui::DragValue::new(&mut inputs.negbinomial_p)
+                        .speed(0.01)
+                        .range(0.0..=1.0),
+                );
+            }
+            Distributions::Binomial => {
+                ui.label("Trials (n)");
+                Self::add_parameter(
+                    ui,
+                    problems,
+                    "binomial_n",
+                    egui::DragValue::new(&mut inputs.binomial_n),
+                );
+                ui.label("Probability (p)");
+                Self::add_parameter(
+                    ui,
+                    problems,
+                    "binomial_p",
+                    egui::DragValue::new(&mut inputs.binomial_p)
+                        .speed(0.01)
+                        .range(0.0..=1.0),
+                );
+            }
+            Distributions::DiscreteUniform => {
+                ui.label("Min");
+                Self::add_parameter(
+                    ui,
+                    problems,
+                    "discrete_min",
+                    egui::DragValue::new(&mut inputs.discrete_min),
+                );
+                ui.label("Max");
+                Self::add_parameter(
+                    ui,
+                    problems,
+                    "discrete_max",
+                    egui::DragValue::new(&mut inputs.discrete_max),
+                );
+            }
+            Distributions::DiscreteTable => {
+                let response = Self::discrete_table_editor(ui, &mut inputs.discrete_table);
+                Self::mark_invalid(ui, response, problems, Field::Parameter("discrete_table"));
+            }
+            Distributions::Empirical => {
+                let response = Self::empirical_editor(ui, inputs);
+                for parameter in ["empirical_path", "empirical_column"] {
+                    Self::mark_invalid(ui, response.clone(), problems, Field::Parameter(parameter));
+                }
+            }
+            Distributions::Metalog => Self::metalog_editor(ui, inputs, problems),
+        }
+    }
+
+    /// Adds `widget` as the editor of distribution parameter `parameter`, marked if it breaks
+    /// one of `problems`.
+    fn add_parameter(
+        ui: &mut egui::Ui,
+        problems: &[InvalidField],
+        parameter: &'static str,
+        widget: impl egui::Widget,
+    ) -> egui::Response {
+        let response = ui.add(widget);
+        Self::mark_invalid(ui, response, problems, Field::Parameter(parameter))
+    }
 
+    /// Outlines `response` in red if `field` breaks one of `problems`, and explains what it
+    /// breaks on hover.
+    fn mark_invalid(
+        ui: &egui::Ui,
+        response: egui::Response,
+        problems: &[InvalidField],
+        field: Field,
+    ) -> egui::Response {
+        let reasons: Vec<String> = problems
+            .iter()
+            .filter(|invalid| invalid.field == field)
+            .map(|invalid| invalid.to_string())
+            .collect();
+        if reasons.is_empty() {
+            return response;
+        }
+        ui.painter().rect_stroke(
+            response.rect.expand(2.0),
+            2.0,
+            egui::Stroke::new(1.5, ui.visuals().error_fg_color),
+            egui::StrokeKind::Outside,
+        );
+        response.on_hover_text(reasons.join("\n"))
+    }
+
+    /// Picks the data file and column of an Empirical distribution and previews the data.
+    fn empirical_editor(ui: &mut egui::Ui, inputs: &mut DistributionInputs) -> egui::Response {
+        ui.vertical(|ui| {
+            fit_panel::show_data_picker(
+                ui,
+                &mut inputs.empirical_path,
+                &mut inputs.empirical_column,
+            );
+            ui.checkbox(&mut inputs.empirical_smoothing, "Kernel smoothing");
+        })
+        .response
+    }
+
+    /// One row per quantile of a Metalog, its limits, and whether a Metalog fits through them.
+    fn metalog_editor(
+        ui: &mut egui::Ui,
+        inputs: &mut DistributionInputs,
+        problems: &[InvalidField],
+    ) {
+        let response = ui.vertical(|ui| {
+            let points = &mut inputs.metalog_points;
+            let mut removed = None;
+            for (i, point) in points.iter_mut().enumerate() {
                 ui.horizontal(|ui| {
-                    ui.label("Number of Months");
+                    ui.label("Probability");
                     ui.add(
-                        egui::DragValue::new(&mut self.number_of_months).range(1.0..=f64::INFINITY),
+                        egui::DragValue::new(&mut point.probability)
+                            .speed(0.01)
+                            .range(0.0..=1.0),
                     );
+                    ui.label("Value");
+                    ui.add(egui::DragValue::new(&mut point.value).speed(0.1));
+                    if ui.small_button("Remove").clicked() {
+                        removed = Some(i);
+                    }
                 });
+            }
+            if let Some(i) = removed {
+                points.remove(i);
+            }
+            if ui.button("Add quantile").clicked() {
+                let point = match points.last() {
+                    Some(last) => MetalogPoint {
+                        probability: 0.5 * (last.probability + 1.0),
+                        value: last.value + 1.0,
+                    },
+                    None => MetalogPoint {
+                        probability: 0.5,
+                        value: 0.0,
+                    },
+                };
+                points.push(point);
+            }
+            ui.horizontal(|ui| {
+                Self::bound_editor(ui, "Lower limit", &mut inputs.metalog_lower);
+                let upper = Self::bound_editor(ui, "Upper limit", &mut inputs.metalog_upper);
+                Self::mark_invalid(ui, upper, problems, Field::Parameter("metalog_upper"));
+            });
+            match Metalog::new(
+                &inputs.metalog_points,
+                inputs.metalog_lower,
+                inputs.metalog_upper,
+            ) {
+                Ok(_) => ui.label(format!("{} terms", inputs.metalog_points.len())),
+                Err(e) => ui.colored_label(ui.visuals().error_fg_color, e.to_string()),
+            };
+        });
+        Self::mark_invalid(
+            ui,
+            response.response,
+            problems,
+            Field::Parameter("metalog_points"),
+        );
+    }
+
+    /// One row per value of a Discrete Table, showing each weight's share of the total.
+    fn discrete_table_editor(ui: &mut egui::Ui, rows: &mut Vec<TableRow>) -> egui::Response {
+        ui.vertical(|ui| {
+            let total: f64 = rows.iter().map(|row| row.weight).sum();
+            let mut removed = None;
+            for (i, row) in rows.iter_mut().enumerate() {
                 ui.horizontal(|ui| {
-                    ui.label("Number of Trials");
+                    ui.label("Value");
+                    ui.add(egui::DragValue::new(&mut row.value).speed(0.1));
+                    ui.label("Weight");
                     ui.add(
-                        egui::DragValue::new(&mut self.number_of_trials).range(1.0..=f64::INFINITY),
+                        egui::DragValue::new(&mut row.weight)
+                            .speed(0.1)
+                            .range(0.0..=f64::INFINITY),
                     );
+                    if total > 0.0 {
+                        ui.label(format!("{:.1}%", row.weight / total * 100.0));
+                    }
+                    if ui.small_button("Remove").clicked() {
+                        removed = Some(i);
+                    }
                 });
+            }
+            if let Some(i) = removed {
+                rows.remove(i);
+            }
+            if ui.button("Add row").clicked() {
+                rows.push(TableRow {
+                    value: 0.0,
+                    weight: 1.0,
+                });
+            }
+        })
+        .response
+    }
 
-                ui.add_space(10.0);
+    /// The editor state of one model input.
+    fn parameter_mut(
+        &mut self,
+        input: CorrelatedInput,
+    ) -> (&mut Distributions, &mut DistributionInputs) {
+        match input {
+            CorrelatedInput::WorkdaysPerMonth => (
+                &mut self.workdays_month_distr,
+                &mut self.workdays_month_inputs,
+            ),
+            CorrelatedInput::RetailersPerDay => (
+                &mut self.retailers_day_distr,
+                &mut self.retailers_day_inputs,
+            ),
+            CorrelatedInput::ConversionRate => (
+                &mut self.conversion_rate_distr,
+                &mut self.conversion_rate_inputs,
+            ),
+            CorrelatedInput::Price => (&mut self.price_distr, &mut self.price_inputs),
+            CorrelatedInput::Units => (&mut self.units_sale_distr, &mut self.units_sale_inputs),
+        }
+    }
 
-                // --- EVENT HANDLING PHASE ---
-                // Logic is only executed when the user clicks the button.
-                let start_button = ui.button("Start Simulation");
-                if start_button.clicked() && !self.is_simulating {
-                    match self.gather_and_validate_values() {
-                        Ok(()) => {
-                            self.is_simulating = true;
-                            self.simulation_result = None;
-                            let (sender, receiver) = mpsc::channel::<SimulationMessage>();
-                            self.simulation_receiver = Some(receiver);
-                            let simulation_data = self.data.clone();
-                            let progress_sender = sender.clone();
-
-                            thread::spawn(move || {
-                                let result =
-                                    start_simulation(&simulation_data, Some(progress_sender));
-                                match result {
-                                    Ok(_) => {
-                                        sender.send(SimulationMessage::Success("".into())).ok()
-                                    }
-                                    Err(e) => {
-                                        sender.send(SimulationMessage::Error(e.to_string())).ok()
-                                    }
-                                };
-                            });
+    /// Renders the distribution fitting window while it is open, and puts the chosen fit into
+    /// its input. The input's truncation bounds are kept.
+    fn show_fitter(&mut self, ctx: &egui::Context) {
+        let Some(fitter) = &mut self.fitter else {
+            return;
+        };
+        let input = fitter.input;
+        let mut open = true;
+        let mut chosen = None;
+        egui::Window::new(format!("Fit {} to data", input))
+            .open(&mut open)
+            .show(ctx, |ui| chosen = fitter.show(ui));
+        if let Some(fit) = chosen {
+            let (distribution, inputs) = self.parameter_mut(input);
+            *distribution = fit.distribution;
+            *inputs = DistributionInputs {
+                lower_bound: inputs.lower_bound,
+                upper_bound: inputs.upper_bound,
+                ..fit.inputs
+            };
+            open = false;
+        }
+        if !open {
+            self.fitter = None;
+        }
+    }
+
+    /// Renders the quantile entry window while it is open, and puts the accepted parameters into
+    /// its input.
+    fn show_quantile_entry(&mut self, ctx: &egui::Context) {
+        let Some(entry) = &mut self.quantile_entry else {
+            return;
+        };
+        let input = entry.input;
+        let mut open = true;
+        let mut accepted = None;
+        egui::Window::new(format!("{} from P10, P50 and P90", input))
+            .open(&mut open)
+            .show(ctx, |ui| accepted = entry.show(ui));
+        if let Some(solved) = accepted {
+            *self.parameter_mut(input).1 = solved;
+            open = false;
+        }
+        if !open {
+            self.quantile_entry = None;
+        }
+    }
+
+    /// Renders the distribution selector and editors of `input`.
+    fn show_distribution_controls(
+        &mut self,
+        ui: &mut egui::Ui,
+        label_text: &str,
+        input: CorrelatedInput,
+    ) {
+        let options = self.probability_distributions.clone();
+        // This input's problems, by parameter name.
+        let problems: Vec<InvalidField> = self
+            .problems
+            .iter()
+            .filter_map(|invalid| match invalid.field {
+                Field::Input(of, parameter) if of == input => Some(InvalidField {
+                    field: Field::Parameter(parameter),
+                    requirement: invalid.requirement.clone(),
+                }),
+                _ => None,
+            })
+            .collect();
+        let (distribution, inputs) = self.parameter_mut(input);
+        let clicked = ui.horizontal(|ui| {
+            ui.label(label_text);
+            let selector =
+                egui::ComboBox::from_label(format!("Select a distribution for {}", label_text))
+                    .selected_text(distribution.to_string())
+                    .show_ui(ui, |ui| {
+                        for option in &options {
+                            ui.selectable_value(distribution, *option, option.to_string());
                         }
-                        Err(err) => {
-                            self.error_message = err;
-                            self.show_error_popup = true;
+                    });
+            Self::mark_invalid(
+                ui,
+                selector.response,
+                &problems,
+                Field::Parameter("distribution"),
+            );
+            Self::input_distributions(ui, *distribution, inputs, &problems);
+            if *distribution != Distributions::Constant {
+                ui.separator();
+                for (label, parameter, bound) in [
+                    ("Lower bound", "lower_bound", &mut inputs.lower_bound),
+                    ("Upper bound", "upper_bound", &mut inputs.upper_bound),
+                ] {
+                    let response = Self::bound_editor(ui, label, bound);
+                    Self::mark_invalid(ui, response, &problems, Field::Parameter(parameter));
+                }
+            }
+            ui.separator();
+            let quantiles_clicked = ui
+                .add_enabled(
+                    quantiles::supports(*distribution),
+                    egui::Button::new("From P10/P50/P90..."),
+                )
+                .on_hover_text("Solves for the parameters that give these percentiles.")
+                .on_disabled_hover_text("This distribution cannot be set from percentiles.")
+                .clicked();
+            let fit_clicked = ui
+                .button("Fit to data...")
+                .on_hover_text("Fits distributions to a column of historical data.")
+                .clicked();
+            (quantiles_clicked, fit_clicked)
+        });
+        let (quantiles_clicked, fit_clicked) = clicked.inner;
+        let entry = quantiles_clicked.then(|| QuantileEntry::new(input, *distribution, inputs));
+        let fitter = fit_clicked.then(|| DistributionFitter::new(input, inputs));
+        if entry.is_some() {
+            self.quantile_entry = entry;
+        }
+        if fitter.is_some() {
+            self.fitter = fitter;
+        }
+    }
+
+    /// A checkbox that turns a truncation bound on or off, with an editor for its value.
+    fn bound_editor(ui: &mut egui::Ui, label: &str, bound: &mut Option<f64>) -> egui::Response {
+        let mut enabled = bound.is_some();
+        let mut response = ui.checkbox(&mut enabled, label);
+        if response.changed() {
+            *bound = enabled.then_some(0.0);
+        }
+        if let Some(value) = bound {
+            response |= ui.add(egui::DragValue::new(value).speed(0.1));
+        }
+        response
+    }
+
+    /// Renders the correlation matrix editor. Only the lower triangle is editable;
+    /// the upper triangle mirrors it and the diagonal is fixed at 1.
+    fn show_correlation_controls(ui: &mut egui::Ui, correlation: &mut CorrelationMatrix) {
+        egui::Grid::new("correlation_matrix")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("");
+                for column in CorrelatedInput::iter() {
+                    ui.label(column.to_string());
+                }
+                ui.end_row();
+                for (i, row) in CorrelatedInput::iter().enumerate() {
+                    ui.label(row.to_string());
+                    for (j, column) in CorrelatedInput::iter().enumerate() {
+                        if j < i {
+                            let mut value = correlation.get(row, column);
+                            let response = ui.add(
+                                egui::DragValue::new(&mut value)
+                                    .speed(0.01)
+                                    .range(-1.0..=1.0),
+                            );
+                            if response.changed() {
+                                correlation.set(row, column, value);
+                            }
+                        } else {
+                            ui.label(format!("{:.2}", correlation.get(row, column)));
                         }
                     }
+                    ui.end_row();
                 }
             });
+    }
+
+    /// Renders the precision target of a convergence-driven run.
+    fn show_convergence_controls(
+        ui: &mut egui::Ui,
+        convergence: &mut ConvergenceTarget,
+        problems: &[InvalidField],
+    ) {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("convergence_measure")
+                .selected_text(convergence.measure.to_string())
+                .show_ui(ui, |ui| {
+                    for option in PrecisionMeasure::iter() {
+                        ui.selectable_value(&mut convergence.measure, option, option.to_string());
+                    }
+                });
+            ui.label("of");
+            egui::ComboBox::from_id_salt("convergence_statistic")
+                .selected_text(convergence.statistic.to_string())
+                .show_ui(ui, |ui| {
+                    for option in TargetStatistic::iter() {
+                        ui.selectable_value(&mut convergence.statistic, option, option.to_string());
+                    }
+                });
+            ui.label("commission at most");
+            let mut percent = convergence.target * 100.0;
+            let response = ui.add(
+                egui::DragValue::new(&mut percent)
+                    .speed(0.01)
+                    .range(0.01..=100.0),
+            );
+            if response.changed() {
+                convergence.target = percent / 100.0;
+            }
+            Self::mark_invalid(ui, response, problems, Field::ConvergenceTarget);
+            ui.label("%");
+        });
+    }
 
-            if self.is_simulating {
-                if let Some(receiver) = &self.simulation_receiver {
-                    if let Ok(result) = receiver.try_recv() {
-                        self.simulation_result = Some(result);
-                        self.is_simulating = false; // The simulation is done
-                        self.simulation_receiver = None; // Clean up the channel
+    /// Renders a sensitivity analysis as a tornado chart and a table of its runs.
+    fn show_sensitivity(ui: &mut egui::Ui, analysis: &SensitivityAnalysis) {
+        ui.horizontal(|ui| {
+            ui.label(format!("Sensitivity table saved to {}", analysis.table_path));
+            if ui.button("Open").clicked() {
+                let _ = opener::open(&analysis.table_path);
+            }
+        });
+        ui.label(format!(
+            "{} per trial (average month): base mean {:.2}, base P10 {:.2}",
+            SENSITIVITY_METRIC, analysis.base.mean, analysis.base.p10
+        ));
+        ui.add_space(5.0);
+        charts::show_tornado(ui, analysis);
+        ui.add_space(5.0);
+        egui::Grid::new("sensitivity_table")
+            .striped(true)
+            .show(ui, |ui| {
+                for header in [
+                    "Input",
+                    "Low value",
+                    "High value",
+                    "Mean at low",
+                    "Mean at high",
+                    "P10 at low",
+                    "P10 at high",
+                ] {
+                    ui.label(header);
+                }
+                ui.end_row();
+                for swing in &analysis.swings {
+                    ui.label(swing.input.to_string());
+                    for value in [
+                        swing.low_value,
+                        swing.high_value,
+                        swing.low.mean,
+                        swing.high.mean,
+                        swing.low.p10,
+                        swing.high.p10,
+                    ] {
+                        ui.label(format!("{:.2}", value));
                     }
+                    ui.end_row();
                 }
-                ui.add_space(10.0);
-                ui.add(egui::ProgressBar::new(self.progress).show_percentage());
-                ui.add_space(5.0);
-                ui.spinner();
+            });
+    }
+
+    /// Renders Sobol indices as a bar chart and a table.
+    fn show_sobol(ui: &mut egui::Ui, analysis: &SobolAnalysis) {
+        ui.horizontal(|ui| {
+            ui.label(format!("Sobol indices saved to {}", analysis.table_path));
+            if ui.button("Open").clicked() {
+                let _ = opener::open(&analysis.table_path);
             }
         });
+        ui.label(format!(
+            "Variance of {} per trial (average month), from {} trials per sample",
+            SENSITIVITY_METRIC, analysis.trials
+        ));
+        ui.add_space(5.0);
+        charts::show_sobol(ui, analysis);
+        ui.add_space(5.0);
+        egui::Grid::new("sobol_table").striped(true).show(ui, |ui| {
+            for header in ["Input", "First order", "Total order"] {
+                ui.label(header);
+            }
+            ui.end_row();
+            for index in &analysis.indices {
+                ui.label(index.input.to_string());
+                ui.label(format!("{:.3}", index.first_order));
+                ui.label(format!("{:.3}", index.total_order));
+                ui.end_row();
+            }
+        });
+    }
+
+    /// Renders how many trials each sampling strategy needs for the same precision.
+    fn show_sampling_comparison(ui: &mut egui::Ui, comparison: &StrategyComparison) {
+        let target = &comparison.target;
+        ui.label(format!(
+            "Trials needed for a {} of the {} commission of at most {:.2}%, from {} \
+             replications of {} trials per strategy",
+            target.measure.to_string().to_lowercase(),
+            target.statistic,
+            target.target * 100.0,
+            comparison.replications,
+            comparison.trials
+        ));
+        ui.add_space(5.0);
+        let baseline = comparison
+            .results
+            .iter()
+            .find(|result| result.strategy == SamplingStrategy::PseudoRandom)
+            .map(|result| result.trials_needed as f64);
+        egui::Grid::new("sampling_comparison")
+            .striped(true)
+            .show(ui, |ui| {
+                for header in [
+                    "Strategy",
+                    "Estimate",
+                    "Precision per replication",
+                    "Trials needed",
+                    "vs pseudo-random",
+                ] {
+                    ui.label(header);
+                }
+                ui.end_row();
+                for result in &comparison.results {
+                    ui.label(result.strategy.to_string());
+                    ui.label(format!("{:.2}", result.estimate));
+                    ui.label(format!("{:.3}%", result.achieved * 100.0));
+                    ui.label(result.trials_needed.to_string());
+                    match baseline {
+                        Some(baseline) => ui.label(format!(
+                            "{:.2}x",
+                            result.trials_needed as f64 / baseline
+                        )),
+                        None => ui.label(""),
+                    };
+                    ui.end_row();
+                }
+            });
+    }
+
+    /// Renders where a finished run's files are and the per-trial statistics of each metric.
+    fn show_summary(ui: &mut egui::Ui, summary: &SimulationSummary) {
+        for (label, path) in [
+            ("Output saved to", &summary.output_path),
+            ("Summary saved to", &summary.summary_path),
+        ] {
+            ui.horizontal(|ui| {
+                ui.label(format!("{} {}", label, path));
+                if ui.button("Open").clicked() {
+                    let _ = opener::open(path);
+                }
+            });
+        }
+        if let Some(convergence) = &summary.convergence {
+            ui.label(convergence.to_string());
+        }
+        if let Some(variance_reduction) = &summary.variance_reduction {
+            ui.label(variance_reduction.to_string());
+        }
+        ui.add_space(5.0);
+        ui.label("Per trial (average month)");
+        egui::Grid::new("summary_statistics")
+            .striped(true)
+            .show(ui, |ui| {
+                let floor = format!("P(< {})", summary.income_floor);
+                for header in [
+                    "",
+                    "Mean",
+                    "Std",
+                    "P5",
+                    "P10",
+                    "P50",
+                    "P90",
+                    "P95",
+                    "VaR 95%",
+                    "CVaR 95%",
+                    floor.as_str(),
+                ] {
+                    ui.label(header);
+                }
+                ui.end_row();
+                for metric in &summary.metrics {
+                    let stats = &metric.per_trial;
+                    ui.label(&metric.metric);
+                    for value in [
+                        stats.mean,
+                        stats.std,
+                        stats.p5,
+                        stats.p10,
+                        stats.p50,
+                        stats.p90,
+                        stats.p95,
+                        stats.value_at_risk,
+                        stats.conditional_value_at_risk,
+                    ] {
+                        ui.label(format!("{:.2}", value));
+                    }
+                    ui.label(format!("{:.1}%", stats.prob_below_floor * 100.0));
+                    ui.end_row();
+                }
+            });
+    }
+}
+
+impl eframe::App for MyEguiApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
+            egui::MenuBar::new().ui(ui, |ui| {
+                ui.add_enabled_ui(!self.is_simulating, |ui| {
+                    ui.menu_button("File", |ui| {
+                        if ui.button("Open Scenario...").clicked() {
+                            self.open_scenario();
+                        }
+                        if ui.button("Save Scenario...").clicked() {
+                            self.save_scenario();
+                        }
+                    });
+                });
+            });
+        });
+
+        let config = self.build_config();
+        if config != self.config {
+            self.revalidate(config);
+        }
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                ui.heading("Monte Carlo Simulation");
+                ui.add_space(10.0);
+
+                // --- DRAWING PHASE ---
+                // The UI is drawn here. No validation or logic is performed in this phase.
+                ui.add_enabled_ui(!self.is_simulating, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Commission rate");
+                        let response =
+                            ui.add(egui::DragValue::new(&mut self.commission).range(0.0..=100.0));
+                        Self::mark_invalid(ui, response, &self.problems, Field::CommissionRate);
+                        ui.label("%");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Transport Bonus");
+                        let response = ui
+                            .add(egui::DragValue::new(&mut self.transport_bonus).range(0.0..=100.0));
+                        Self::mark_invalid(ui, response, &self.problems, Field::TransportBonus);
+                        ui.label("%");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Monthly income floor");
+                        let response = ui.add(
+                            egui::DragValue::new(&mut self.income_floor).range(0.0..=f64::INFINITY),
+                        );
+                        Self::mark_invalid(ui, response, &self.problems, Field::IncomeFloor);
+                    });
+
+                    self.show_distribution_controls(
+                        ui,
+                        "Price to retailer",
+                        CorrelatedInput::Price,
+                    );
+                    self.show_distribution_controls(
+                        ui,
+                        "Retailers/Day",
+                        CorrelatedInput::RetailersPerDay,
+                    );
+                    self.show_distribution_controls(
+                        ui,
+                        "Workdays/Month",
+                        CorrelatedInput::WorkdaysPerMonth,
+                    );
+                    self.show_distribution_controls(ui, "Units/Sale", CorrelatedInput::Units);
+                    self.show_distribution_controls(
+                        ui,
+                        "Conversion Rate",
+                        CorrelatedInput::ConversionRate,
+                    );
+
+                    let response = ui.checkbox(&mut self.correlate_inputs, "Correlate inputs");
+                    Self::mark_invalid(ui, response, &self.problems, Field::Correlation);
+                    if self.correlate_inputs {
+                        Self::show_correlation_controls(ui, &mut self.correlation);
+                    }
+
+                    ui.horizontal(|ui| {
+                        ui.label("Number of Months");
+                        let response = ui.add(
+                            egui::DragValue::new(&mut self.number_of_months).range(1..=usize::MAX),
+                        );
+                        Self::mark_invalid(ui, response, &self.problems, Field::NumMonths);
+                    });
+                    ui.checkbox(&mut self.stop_on_convergence, "Stop when precise enough");
+                    if self.stop_on_convergence {
+                        Self::show_convergence_controls(
+                            ui,
+                            &mut self.convergence,
+                            &self.problems,
+                        );
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label(if self.stop_on_convergence {
+                            "Max Trials"
+                        } else {
+                            "Number of Trials"
+                        });
+                        let response = ui.add(
+                            egui::DragValue::new(&mut self.number_of_trials).range(1..=usize::MAX),
+                        );
+                        Self::mark_invalid(ui, response, &self.problems, Field::Trials);
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Sampling");
+                        egui::ComboBox::from_id_salt("sampling_strategy")
+                            .selected_text(self.sampling.to_string())
+                            .show_ui(ui, |ui| {
+                                for option in SamplingStrategy::iter() {
+                                    ui.selectable_value(
+                                        &mut self.sampling,
+                                        option,
+                                        option.to_string(),
+                                    );
+                                }
+                            })
+                            .response
+                            .on_hover_text(
+                                "How workdays/month, retailers/day and conversion rate are \
+                                 spread over the trials.",
+                            );
+                    });
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.antithetic, "Antithetic pairs").on_hover_text(
+                            "Pairs each trial with one that mirrors all of its draws.",
+                        );
+                        let response = ui
+                            .checkbox(&mut self.control_variate, "Control variate")
+                            .on_hover_text(
+                                "Sharpens the mean commission with each trial's expected sale \
+                                 value, whose mean is known. Needs independent inputs.",
+                            );
+                        Self::mark_invalid(ui, response, &self.problems, Field::ControlVariate);
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Seed");
+                        ui.add(egui::DragValue::new(&mut self.seed));
+                        if ui.button("Randomize").clicked() {
+                            self.seed = rand::random::<u32>().into();
+                        }
+                    });
+
+                    ui.add_space(10.0);
+                    if !self.problems.is_empty() {
+                        let color = ui.visuals().error_fg_color;
+                        ui.colored_label(color, "Fix the inputs marked in red to run:");
+                        for invalid in &self.problems {
+                            ui.colored_label(color, format!("• {}", invalid));
+                        }
+                        ui.add_space(5.0);
+                    }
+
+                    // --- EVENT HANDLING PHASE ---
+                    // Logic is only executed when the user clicks the button.
+                    ui.horizontal(|ui| {
+                        // Every run validates the same inputs, so none can start while any
+                        // of them is invalid.
+                        ui.add_enabled_ui(self.problems.is_empty(), |ui| {
+                            let start_button = ui.button("Start Simulation");
+                            if start_button.clicked() && !self.is_simulating {
+                                self.launch(|config, sender, cancellation| {
+                                    let _ = start_simulation(
+                                        config,
+                                        Path::new(DEFAULT_OUTPUT_PATH),
+                                        Some(sender),
+                                        cancellation,
+                                    );
+                                });
+                            }
+                            let sensitivity_button = ui
+                                .button("Run Sensitivity Analysis")
+                                .on_hover_text(
+                                    "Swings each input between its P10 and P90 (commission and \
+                                     transport bonus by 10%) and compares the outcomes.",
+                                );
+                            if sensitivity_button.clicked() && !self.is_simulating {
+                                self.launch(|config, sender, cancellation| {
+                                    let _ = run_sensitivity(
+                                        config,
+                                        Path::new(DEFAULT_SENSITIVITY_PATH),
+                                        Some(sender),
+                                        cancellation,
+                                    );
+                                });
+                            }
+                            let sobol_button = ui.button("Compute Sobol Indices").on_hover_text(
+                                "Splits the variance of income between the stochastic inputs. \
+                                 Runs the trial count (2 + 5) times.",
+                            );
+                            if sobol_button.clicked() && !self.is_simulating {
+                                self.launch(|config, sender, cancellation| {
+                                    let _ = run_sobol(
+                                        config,
+                                        Path::new(DEFAULT_SOBOL_PATH),
+                                        Some(sender),
+                                        cancellation,
+                                    );
+                                });
+                            }
+                            let sampling_button = ui
+                                .button("Compare Sampling Strategies")
+                                .on_hover_text(
+                                    "Estimates how many trials each sampling strategy needs for the \
+                                     same precision. Runs the trial count three times.",
+                                );
+                            if sampling_button.clicked() && !self.is_simulating {
+                                self.launch(|config, sender, cancellation| {
+                                    let _ = compare_strategies(config, Some(sender), cancellation);
+                                });
+                            }
+                        });
+                    });
+                });
+
+                if self.is_simulating {
+                    if let Some(receiver) = &self.simulation_receiver {
+                        while let Ok(message) = receiver.try_recv() {
+                            match message {
+                                SimulationMessage::Progress(progress) => self.progress = progress,
+                                SimulationMessage::Error(err) => {
+                                    // Errors with the inputs are marked on their editors;
+                                    // anything else needs the popup.
+                                    let failed_fields = err.invalid_fields();
+                                    if failed_fields.is_empty() {
+                                        self.error_message = err.to_string();
+                                        self.show_error_popup = true;
+                                    }
+                                    // They stay marked until the inputs are edited.
+                                    self.problems.extend(failed_fields);
+                                    self.is_simulating = false;
+                                }
+                                SimulationMessage::Success(summary) => {
+                                    self.charts = Some(ResultCharts::new(&summary));
+                                    self.simulation_result =
+                                        Some(SimulationMessage::Success(summary));
+                                    self.is_simulating = false;
+                                }
+                                SimulationMessage::Sensitivity(analysis) => {
+                                    self.simulation_result =
+                                        Some(SimulationMessage::Sensitivity(analysis));
+                                    self.is_simulating = false;
+                                }
+                                SimulationMessage::Sobol(analysis) => {
+                                    self.simulation_result =
+                                        Some(SimulationMessage::Sobol(analysis));
+                                    self.is_simulating = false;
+                                }
+                                SimulationMessage::Sampling(comparison) => {
+                                    self.simulation_result =
+                                        Some(SimulationMessage::Sampling(comparison));
+                                    self.is_simulating = false;
+                                }
+                                SimulationMessage::Cancelled => {
+                                    self.simulation_result = Some(SimulationMessage::Cancelled);
+                                    self.is_simulating = false;
+                                }
+                            }
+                        }
+                        if !self.is_simulating {
+                            self.simulation_receiver = None; // Clean up the channel
+                        }
+                    }
+                    ui.add_space(10.0);
+                    ui.add(egui::ProgressBar::new(self.progress).show_percentage());
+                    ui.add_space(5.0);
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        let cancelling = self.cancellation.is_cancelled();
+                        if ui
+                            .add_enabled(!cancelling, egui::Button::new("Cancel"))
+                            .clicked()
+                        {
+                            self.cancellation.cancel();
+                        }
+                        if cancelling {
+                            ui.label("Cancelling...");
+                        }
+                    });
+                    ctx.request_repaint();
+                } else {
+                    match &self.simulation_result {
+                        Some(SimulationMessage::Success(summary)) => {
+                            ui.add_space(10.0);
+                            Self::show_summary(ui, summary);
+                            if let Some(charts) = &mut self.charts {
+                                ui.add_space(10.0);
+                                charts.show(ui);
+                            }
+                        }
+                        Some(SimulationMessage::Sensitivity(analysis)) => {
+                            ui.add_space(10.0);
+                            Self::show_sensitivity(ui, analysis);
+                        }
+                        Some(SimulationMessage::Sobol(analysis)) => {
+                            ui.add_space(10.0);
+                            Self::show_sobol(ui, analysis);
+                        }
+                        Some(SimulationMessage::Sampling(comparison)) => {
+                            ui.add_space(10.0);
+                            Self::show_sampling_comparison(ui, comparison);
+                        }
+                        Some(SimulationMessage::Cancelled) => {
+                            ui.add_space(10.0);
+                            ui.label("Simulation cancelled.");
+                        }
+                        _ => {}
+                    }
+                }
+            });
+        });
+
+        self.show_fitter(ctx);
+        self.show_quantile_entry(ctx);
 
         // --- POPUP DISPLAY ---
         // This reads the state set in the event handling phase. It doesn't modify state itself.
@@ -0,0 +1,18 @@
+pub mod config;
+pub mod convergence;
+pub mod correlation;
+pub mod distributions;
+pub mod empirical;
+pub mod errors;
+pub mod fitting;
+pub mod mcs;
+pub mod message;
+pub mod metalog;
+pub mod quantiles;
+pub mod sampling;
+pub mod scenario;
+pub mod sensitivity;
+pub mod sobol;
+pub mod summary;
+pub mod validation;
+pub mod variance_reduction;
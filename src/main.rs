@@ -1,11 +1,10 @@
+mod charts;
+mod fit_panel;
 mod gui;
+mod quantile_panel;
 use eframe::egui::ViewportBuilder;
-mod distributions;
-mod mcs;
-mod message;
 use eframe::run_native;
 use gui::MyEguiApp;
-mod errors;
 
 fn main() {
     let native_options = eframe::NativeOptions {
@@ -1,64 +1,254 @@
+use crate::config::SimulationConfig;
+use crate::convergence::ConvergenceReport;
+use crate::correlation::{CholeskyFactor, CorrelatedInput, NUM_CORRELATED_INPUTS};
 use crate::distributions::{DistributionInputs, Distributions};
-use crate::errors::DistributionError;
-use crate::message::SimulationMessage;
+use crate::empirical::{self, EmpiricalData};
+use crate::errors::{DistributionError, SimulationError};
+use crate::message::{CancellationToken, SimulationMessage};
+use crate::metalog::Metalog;
+use crate::sampling::TrialDesign;
+use crate::summary::{SimulationSummary, summarize};
+use crate::variance_reduction::{self, ControlVariate, VarianceReduction};
 use ndarray::*;
 use ndarray_rand::{
     RandomExt,
-    rand_distr::{Bernoulli, Normal, Pert, Triangular, Uniform},
+    rand::{Rng, SeedableRng},
+    rand_distr::{
+        Bernoulli, Beta as BetaDistribution, Binomial, Gamma, LogNormal, Normal, Open01, Pert,
+        Poisson, Triangular, Uniform, Weibull, WeightedAliasIndex,
+    },
 };
 use polars::prelude::*;
+use rand_chacha::ChaCha12Rng;
 use rayon::prelude::*;
+use statrs::distribution::{Beta, ContinuousCDF, DiscreteCDF};
 use std::{
-    collections::HashMap,
     fs::{self, File},
-    path::PathBuf,
-    sync::mpsc::Sender,
+    ops::Range,
+    path::Path,
+    sync::{Arc, mpsc::Sender},
 };
 
+/// Where the GUI writes its output, relative to the working directory.
+pub const DEFAULT_OUTPUT_PATH: &str = "mcs_data/output.parquet";
+
+/// Number of trials generated, aggregated and written per parquet row group.
+/// Peak memory is bounded by this rather than by the total trial count.
+const TRIALS_PER_BATCH: i64 = 250;
+
+/// RNG streams that are not tied to one input's own draws. Each input's draws use the stream
+/// numbered by its position in [`CorrelatedInput`].
+const CONVERSIONS_STREAM: u8 = NUM_CORRELATED_INPUTS as u8;
+const COPULA_STREAM: u8 = NUM_CORRELATED_INPUTS as u8 + 1;
+
+/// Seed of each stochastic input's RNG streams, in [`CorrelatedInput`] order. A normal run
+/// seeds them all with the master seed; the Sobol analysis reseeds single inputs to resample
+/// them while the others stay put.
+pub(crate) type InputSeeds = [u64; NUM_CORRELATED_INPUTS];
+
+/// Runs the simulation described by `config`, writes it to `output_path`, and returns the
+/// summary statistics computed from the output along with where the files were written.
+/// Every trial draws from its own RNG stream derived from the seed and the trial index,
+/// so the output depends only on the configuration.
+///
+/// With a convergence target, trials run in batches until the target is met or
+/// `config.trials` is reached, and the precision achieved is part of the summary. So is the
+/// variance-reduced mean commission, if the run uses antithetic pairs or a control variate.
+///
+/// The outcome is also reported through `progress_sender`, as [`SimulationMessage::Cancelled`]
+/// if `cancellation` was triggered before the run finished.
 pub fn start_simulation(
-    data: &HashMap<String, (Distributions, DistributionInputs)>,
+    config: &SimulationConfig,
+    output_path: &Path,
     progress_sender: Option<Sender<SimulationMessage>>,
-) -> Result<(), PolarsError> {
-    if !data.is_empty() {
-        let sale_val = col("units") * col("price") * col("was_converted");
-        let commission = sale_val.clone() * col("commission_rate");
-        let transport_bonus = data.get("Transport_Bonus").ok_or_else(|| PolarsError::ComputeError(format!("'{}' parameter not found", "Transport_Bonus").into()))?.1.constant_val;
-        let lf = create_data(data, &progress_sender)?
-            .with_columns([
-                sale_val.alias("Sale Value"),
-                commission.alias("Commissions"),
-                (col("was_converted").sum().over([col("distributor_id")])
-                    / col("was_converted").len().over([col("distributor_id")]))
-                .alias("Conversion Probability"),
-            ])
-            .group_by(["distributor_id", "month"])
-            .agg([
-                col("Commissions").sum(),
-                col("Sale Value").sum(),
-                col("units").sum(),
-                col("Conversion Probability").unique().get(0),
-            ]).with_column((col("Commissions")+(col("units")*lit(transport_bonus))).alias("Comission with bonus"));
-        let save = save_dataframe(lf);
-        match save {
-            Ok(path) => {
-                if let Some(sender) = progress_sender {
-                    let _ = sender.send(SimulationMessage::Success(path));
-                }
-            }
-            Err(e) => {
-                if let Some(sender) = progress_sender {
-                    let _ = sender.send(SimulationMessage::Error(e.to_string()));
-                }
-            }
+    cancellation: &CancellationToken,
+) -> Result<SimulationSummary, SimulationError> {
+    let save = save_dataframe(config, output_path, &progress_sender, cancellation).and_then(
+        |(path, precision)| {
+            let mut summary = summarize(&path, config.income_floor)?;
+            summary.convergence = precision.convergence;
+            summary.variance_reduction = precision.variance_reduction;
+            Ok(summary)
+        },
+    );
+    if let Some(sender) = progress_sender {
+        let message = match &save {
+            Ok(summary) => SimulationMessage::Success(summary.clone()),
+            Err(SimulationError::Cancelled) => SimulationMessage::Cancelled,
+            Err(e) => SimulationMessage::Error(e.clone()),
         };
+        let _ = sender.send(message);
     }
-    Ok(())
+    save
+}
+
+/// Aggregates the per-visit rows of a batch of trials into one row per distributor and month.
+fn aggregate(lf: LazyFrame, transport_bonus: f64) -> LazyFrame {
+    let sale_val = col("units") * col("price") * col("was_converted");
+    let commission = sale_val.clone() * col("commission_rate");
+    lf.with_columns([
+        sale_val.alias("Sale Value"),
+        commission.alias("Commissions"),
+        (col("was_converted").sum().over([col("distributor_id")])
+            / col("was_converted").len().over([col("distributor_id")]))
+        .alias("Conversion Probability"),
+    ])
+    .group_by(["distributor_id", "month"])
+    .agg([
+        col("Commissions").sum(),
+        col("Sale Value").sum(),
+        col("units").sum(),
+        col("was_converted").sum().alias("Conversions"),
+        col("Conversion Probability").unique().get(0),
+    ])
+    .with_column(
+        (col("Commissions") + (col("units") * lit(transport_bonus))).alias("Comission with bonus"),
+    )
 }
 
-fn save_dataframe(lf: LazyFrame) -> Result<String, PolarsError> {
-    let mut df = lf
-        .collect()?
-        .sort(
+/// How precise a run's estimate of the mean commission was, where the run measured it.
+#[derive(Debug, Default)]
+struct RunPrecision {
+    convergence: Option<ConvergenceReport>,
+    variance_reduction: Option<VarianceReduction>,
+}
+
+/// Writes the simulation output to `full_path` and returns its absolute path, along with
+/// the precision reports the run asked for.
+/// A run that fails or is cancelled leaves no partial file behind, and an invalid `config`
+/// none at all.
+fn save_dataframe(
+    config: &SimulationConfig,
+    full_path: &Path,
+    progress_sender: &Option<Sender<SimulationMessage>>,
+    cancellation: &CancellationToken,
+) -> Result<(String, RunPrecision), SimulationError> {
+    config.validate()?;
+    let io_error = |e| SimulationError::io(full_path, e);
+    if let Some(parent_dir) = full_path.parent() {
+        fs::create_dir_all(parent_dir).map_err(io_error)?;
+    }
+    let file = File::create(full_path).map_err(io_error)?;
+    let precision = match write_batches(config, full_path, file, progress_sender, cancellation) {
+        Ok(precision) => precision,
+        Err(e) => {
+            let _ = fs::remove_file(full_path);
+            return Err(e);
+        }
+    };
+    let absolute_path = fs::canonicalize(full_path)
+        .map_err(io_error)?
+        .to_string_lossy()
+        .to_string();
+    Ok((absolute_path, precision))
+}
+
+/// Runs the trials in batches of [`TRIALS_PER_BATCH`], appending each aggregated and sorted
+/// batch to `file`, opened at `path`, as its own row group. Distributor ids increase from one
+/// batch to the next, so the file as a whole stays ordered by `distributor_id`, `month`.
+///
+/// With a convergence target, the precision is checked after every batch and the run stops
+/// as soon as it is met. With antithetic pairs or a control variate, the mean commission is
+/// estimated with them once the run is over.
+fn write_batches(
+    config: &SimulationConfig,
+    path: &Path,
+    file: File,
+    progress_sender: &Option<Sender<SimulationMessage>>,
+    cancellation: &CancellationToken,
+) -> Result<RunPrecision, SimulationError> {
+    let mut file = Some(file);
+    let mut writer = None;
+    let control = config
+        .control_variate
+        .then(|| ControlVariate::new(config))
+        .transpose()?;
+    let reduce_variance = config.antithetic || control.is_some();
+    let mut trial_commissions = Vec::new();
+    let mut trial_controls = Vec::new();
+    let mut precision = RunPrecision::default();
+
+    run_batches(config, progress_sender, cancellation, |df, _| {
+        if let Some(file) = file.take() {
+            let batched = ParquetWriter::new(file).batched(df.schema());
+            writer = Some(batched.map_err(|e| SimulationError::io(path, e))?);
+        }
+        if let Some(writer) = writer.as_mut() {
+            writer
+                .write_batch(&df)
+                .map_err(|e| SimulationError::io(path, e))?;
+        }
+
+        if let Some(control) = &control {
+            trial_controls.extend(control.values(&trial_averages(df.clone(), "Conversions")?));
+        }
+        if config.convergence.is_some() || reduce_variance {
+            trial_commissions.extend(trial_averages(df, "Commissions")?);
+        }
+        if let Some(target) = &config.convergence {
+            let report = target.report(&trial_commissions);
+            let converged = report.converged;
+            precision.convergence = Some(report);
+            return Ok(converged);
+        }
+        Ok(false)
+    })?;
+    if let Some(writer) = writer {
+        writer.finish().map_err(|e| SimulationError::io(path, e))?;
+    }
+    if reduce_variance {
+        precision.variance_reduction = variance_reduction::estimate(
+            &trial_commissions,
+            control
+                .as_ref()
+                .map(|control| (control, trial_controls.as_slice())),
+            config.antithetic,
+        );
+    }
+    Ok(precision)
+}
+
+/// Runs the trials of `config` in batches of [`TRIALS_PER_BATCH`] and hands each aggregated
+/// batch, sorted by `distributor_id`, `month`, to `on_batch` along with the trials it holds.
+/// Stops early once `on_batch` returns `true`.
+pub(crate) fn run_batches(
+    config: &SimulationConfig,
+    progress_sender: &Option<Sender<SimulationMessage>>,
+    cancellation: &CancellationToken,
+    on_batch: impl FnMut(DataFrame, Range<i64>) -> Result<bool, SimulationError>,
+) -> Result<(), SimulationError> {
+    let seeds = [config.seed; NUM_CORRELATED_INPUTS];
+    run_batches_seeded(config, &seeds, progress_sender, cancellation, on_batch)
+}
+
+/// [`run_batches`] with each stochastic input's RNG streams seeded separately.
+pub(crate) fn run_batches_seeded(
+    config: &SimulationConfig,
+    seeds: &InputSeeds,
+    progress_sender: &Option<Sender<SimulationMessage>>,
+    cancellation: &CancellationToken,
+    mut on_batch: impl FnMut(DataFrame, Range<i64>) -> Result<bool, SimulationError>,
+) -> Result<(), SimulationError> {
+    let n = config.trials as i64;
+    let factor = if config.correlation.is_identity() {
+        None
+    } else {
+        Some(config.correlation.cholesky()?)
+    };
+    let design = TrialDesign::new(config.sampling, seeds, config.trials);
+
+    for batch_start in (1..=n).step_by(TRIALS_PER_BATCH as usize) {
+        let batch = batch_start..(batch_start + TRIALS_PER_BATCH).min(n + 1);
+        let lf = create_data(
+            config,
+            factor.as_ref(),
+            design.as_ref(),
+            seeds,
+            batch.clone(),
+            progress_sender,
+            cancellation,
+        )?;
+        let mut df = aggregate(lf, config.transport_bonus).collect()?.sort(
             ["distributor_id", "month"],
             SortMultipleOptions {
                 descending: vec![false],
@@ -68,108 +258,217 @@ fn save_dataframe(lf: LazyFrame) -> Result<String, PolarsError> {
                 limit: None,
             },
         )?;
-    
-    let output_dir = "mcs_data";
-    let output_filename = "output.parquet";
-    let mut full_path = PathBuf::from(output_dir);
-    full_path.push(output_filename);
-    if let Some(parent_dir) = full_path.parent() {
-        fs::create_dir_all(parent_dir)?;
+        df.align_chunks_par();
+        if cancellation.is_cancelled() {
+            return Err(SimulationError::Cancelled);
+        }
+        if on_batch(df, batch)? {
+            break;
+        }
     }
-    let file = File::create(&full_path)?;
-    ParquetWriter::new(file).finish(&mut df)?;
-    let absolute_path = fs::canonicalize(&full_path)?.to_string_lossy().to_string();
-    Ok(absolute_path)
+    Ok(())
+}
+
+/// Each trial's average month of `metric`, from an aggregated batch, in trial order.
+pub(crate) fn trial_averages(df: DataFrame, metric: &str) -> Result<Vec<f64>, PolarsError> {
+    let trials = df
+        .lazy()
+        .group_by_stable([col("distributor_id")])
+        .agg([col(metric).mean()])
+        .collect()?;
+    Ok(trials.column(metric)?.f64()?.into_no_null_iter().collect())
 }
 
+/// Generates the per-visit rows for the trials in `trials`. When `factor` is given, the inputs
+/// are drawn through a Gaussian copula with that Cholesky factor instead of independently.
+/// When `design` is given, it supplies the trial-level inputs' uniforms.
 fn create_data(
-    data: &HashMap<String, (Distributions, DistributionInputs)>,
+    config: &SimulationConfig,
+    factor: Option<&CholeskyFactor>,
+    design: Option<&TrialDesign>,
+    seeds: &InputSeeds,
+    trials: Range<i64>,
     progress_sender: &Option<Sender<SimulationMessage>>,
-) -> Result<LazyFrame, PolarsError> {
-    let n = data.get("Trials").ok_or_else(|| PolarsError::ComputeError(format!("'{}' parameter not found", "Trials").into()))?.1.constant_val as i64 + 1;
-
-    let commissions = data.get("Commission_Rate").ok_or_else(|| PolarsError::ComputeError(format!("'{}' parameter not found", "Commission_Rate").into()))?.1.constant_val / 100.0;
-    
-    let prices = data.get("Prices").ok_or_else(|| PolarsError::ComputeError(format!("'{}' parameter not found", "Prices").into()))?;
-    
-    let retailers_per_day = data.get("Retailers_per_Day").ok_or_else(|| PolarsError::ComputeError(format!("'{}' parameter not found", "Retailers_per_Day").into()))?;
-    
-    let workdays_per_month = data.get("Workdays_per_Month").ok_or_else(|| PolarsError::ComputeError(format!("'{}' parameter not found", "Workdays_per_Month").into()))?;
-    
-    let conversion_rate = data.get("Conversion_Rate").ok_or_else(|| PolarsError::ComputeError(format!("'{}' parameter not found", "Conversion_Rate").into()))?;
-    
-    let units = data.get("Units").ok_or_else(|| PolarsError::ComputeError(format!("'{}' parameter not found", "Units").into()))?;
-    
-    let num_months = data.get("Number_of_Months").ok_or_else(|| PolarsError::ComputeError(format!("'{}' parameter not found", "Number_of_Months").into()))?.1.constant_val.round() as usize;
-    let range = 1..n;
-
-    let lfs: Result<Vec<LazyFrame>, PolarsError> = range
+    cancellation: &CancellationToken,
+) -> Result<LazyFrame, SimulationError> {
+    let lfs: Result<Vec<LazyFrame>, SimulationError> = trials
         .into_par_iter()
         .map(|i| {
-            create_lazyframes(
-                workdays_per_month,
-                conversion_rate,
-                retailers_per_day,
-                num_months,
-                prices,
-                units,
-                i,
-                n,
-                commissions,
-                progress_sender,
-            )
+            if cancellation.is_cancelled() {
+                return Err(SimulationError::Cancelled);
+            }
+            create_lazyframes(config, factor, design, seeds, i, progress_sender)
         })
         .collect();
     let lf = concat(lfs?, UnionArgs::default())?;
     Ok(lf)
 }
 
+/// Generates the per-visit rows of trial `i`. Each stochastic input draws from its own RNG
+/// stream, seeded from `seeds`; when `factor` is given they share one copula stream instead.
+/// A `design` replaces the trial-level inputs' random uniforms, or the copula's independent
+/// trial-level normals, with the trial's point. With antithetic variates, the second trial of
+/// each pair mirrors every draw of the first, design points included.
 fn create_lazyframes(
-    workdays_per_month: &(Distributions, DistributionInputs),
-    conversion_rate: &(Distributions, DistributionInputs),
-    retailers_per_day: &(Distributions, DistributionInputs),
-    num_months: usize,
-    prices: &(Distributions, DistributionInputs),
-    units: &(Distributions, DistributionInputs),
+    config: &SimulationConfig,
+    factor: Option<&CholeskyFactor>,
+    design: Option<&TrialDesign>,
+    seeds: &InputSeeds,
     i: i64,
-    n: i64,
-    commissions: f64,
     progress_sender: &Option<Sender<SimulationMessage>>,
-) -> Result<LazyFrame, PolarsError> {    
-    let err_msg = |name: &str| PolarsError::ComputeError(format!("{} array was empty", name).into());
+) -> Result<LazyFrame, SimulationError> {
+    let role = PairRole::of(config.antithetic, i);
+    let stream_trial = role.stream_trial(i);
+    let input_rng =
+        |input: CorrelatedInput, stream: u8| trial_rng(seeds[input as usize], stream_trial, stream);
+    let mut copula_rng = trial_rng(config.seed, stream_trial, COPULA_STREAM);
+    let point = design.map(|design| design.point(stream_trial));
+    let sampling = |input: CorrelatedInput| {
+        move |source| SimulationError::Sampling {
+            input,
+            trial: Some(i),
+            source,
+        }
+    };
+    let copula = factor.map(|factor| {
+        let trial_factors = match point {
+            Some(point) => {
+                let standard_normal = statrs::distribution::Normal::standard();
+                point.map(|u| standard_normal.inverse_cdf(u))
+            }
+            None => factor.trial_factors(&mut copula_rng),
+        };
+        (factor, trial_factors)
+    });
+    let draw_trial_input = |params: &(Distributions, DistributionInputs),
+                            input: CorrelatedInput|
+     -> Result<f64, SimulationError> {
+        let values = match &copula {
+            Some((factor, trial_factors)) => create_array_from_normals(
+                params,
+                &arr1(&[role.normal(factor.trial_normal(input, trial_factors))]),
+            ),
+            None => match point {
+                Some(point) => create_array_from_uniforms(
+                    params,
+                    &arr1(&[role.uniform(point[input as usize])]),
+                ),
+                None => create_paired_array(params, 1, &mut input_rng(input, input as u8), role),
+            },
+        };
+        Ok(values.map_err(sampling(input))?[0])
+    };
+
+    let conversion_rate = truncated_conversion_rate(config);
 
-    let workdays_per_month = *create_array(workdays_per_month, 1)?.round().get(0).ok_or_else(|| err_msg("Workdays"))? as usize;
-    let mut conversion_rate = *create_array(conversion_rate, 1)?.get(0).ok_or_else(|| err_msg("Workdays"))? / 100.0;
-    conversion_rate = conversion_rate.clamp(0.01, 0.99);
+    let workdays_per_month = to_count(
+        config.workdays_per_month.0,
+        draw_trial_input(
+            &config.workdays_per_month,
+            CorrelatedInput::WorkdaysPerMonth,
+        )?,
+    ) as usize;
+    let conversion_rate =
+        draw_trial_input(&conversion_rate, CorrelatedInput::ConversionRate)? / 100.0;
     let conversion_rate = DistributionInputs {
         bernoulli_prob: conversion_rate,
         ..Default::default()
     };
 
-    let retailers_per_day = *create_array(retailers_per_day, 1)?.round().get(0).ok_or_else(|| err_msg("Workdays"))?  as usize;
-    let len = *&workdays_per_month * &retailers_per_day * &num_months;
+    let retailers_per_day = to_count(
+        config.retailers_per_day.0,
+        draw_trial_input(&config.retailers_per_day, CorrelatedInput::RetailersPerDay)?,
+    ) as usize;
+    // A trial without visits gets one empty visit a month, so it shows up in the output as
+    // earning nothing rather than vanishing from it.
+    let idle = workdays_per_month * retailers_per_day == 0;
+    let (workdays_per_month, retailers_per_day) = if idle {
+        (1, 1)
+    } else {
+        (workdays_per_month, retailers_per_day)
+    };
+    let num_months = config.num_months;
+    let len = workdays_per_month * retailers_per_day * num_months;
     let dist_ids = Array1::<i64>::ones(len) * i;
-    let commissions = Array1::<f64>::ones(len) * commissions;
+    let commissions = Array1::<f64>::ones(len) * (config.commission_rate / 100.0);
     let months = create_months_array(&workdays_per_month, &retailers_per_day, &num_months);
-    let conversions = create_array(&(Distributions::Bernoulli, conversion_rate), len);
-    let prices = create_array(prices, len);
-    let units = create_array(units, len)?.round();
+    let mut conversions = create_paired_array(
+        &(Distributions::Bernoulli, conversion_rate),
+        len,
+        &mut input_rng(CorrelatedInput::ConversionRate, CONVERSIONS_STREAM),
+        role,
+    )
+    .map_err(sampling(CorrelatedInput::ConversionRate))?;
+    let (prices, units) = match &copula {
+        Some((factor, trial_factors)) => {
+            let [price_normals, units_normals] = factor
+                .visit_normals(trial_factors, len, &mut copula_rng)
+                .map(|normals| normals.mapv(|z| role.normal(z)));
+            (
+                create_array_from_normals(&config.prices, &price_normals)
+                    .map_err(sampling(CorrelatedInput::Price))?,
+                create_array_from_normals(&config.units, &units_normals)
+                    .map_err(sampling(CorrelatedInput::Units))?,
+            )
+        }
+        None => (
+            create_paired_array(
+                &config.prices,
+                len,
+                &mut input_rng(CorrelatedInput::Price, CorrelatedInput::Price as u8),
+                role,
+            )
+            .map_err(sampling(CorrelatedInput::Price))?,
+            create_paired_array(
+                &config.units,
+                len,
+                &mut input_rng(CorrelatedInput::Units, CorrelatedInput::Units as u8),
+                role,
+            )
+            .map_err(sampling(CorrelatedInput::Units))?,
+        ),
+    };
+    let mut units = units.mapv(|x| to_count(config.units.0, x));
+    if idle {
+        conversions.fill(0.0);
+        units.fill(0.0);
+    }
     let lf = df! (
     "distributor_id" => dist_ids.to_vec(),
     "month" => months.to_vec(),
     "commission_rate" => commissions.to_vec(),
-    "was_converted" => conversions?.to_vec(),
-    "price" => prices?.to_vec(),
+    "was_converted" => conversions.to_vec(),
+    "price" => prices.to_vec(),
     "units"=>units.to_vec(),
     )?
     .lazy();
     if let Some(sender) = progress_sender {
-        let _ = sender.send(SimulationMessage::Progress(i as f32 / n as f32));
-    } else {
+        let _ = sender.send(SimulationMessage::Progress(i as f32 / config.trials as f32));
     }
     Ok(lf)
 }
 
+/// The conversion rate input truncated to 0-100%, since a rate outside it means nothing.
+pub(crate) fn truncated_conversion_rate(
+    config: &SimulationConfig,
+) -> (Distributions, DistributionInputs) {
+    let (distribution, mut inputs) = config.conversion_rate.clone();
+    inputs.lower_bound = Some(inputs.lower_bound.map_or(0.0, |bound| bound.max(0.0)));
+    inputs.upper_bound = Some(inputs.upper_bound.map_or(100.0, |bound| bound.min(100.0)));
+    (distribution, inputs)
+}
+
+/// Turns a draw of a count-valued input into a count. Discrete distributions already draw
+/// whole numbers; continuous draws are rounded. Negative draws count as zero.
+pub(crate) fn to_count(distribution: Distributions, value: f64) -> f64 {
+    let count = if distribution.is_discrete() {
+        value
+    } else {
+        value.round()
+    };
+    count.max(0.0)
+}
+
 fn create_months_array(
     workdays_per_month: &usize,
     retailers_per_day: &usize,
@@ -180,41 +479,694 @@ fn create_months_array(
     Array1::from_shape_fn(total_size, |i| ((i / block_size) + 1) as i64)
 }
 
+/// Builds RNG stream `stream` of trial `i`. The seed, the trial index and the stream together
+/// form the ChaCha key, so every trial gets independent streams no matter which thread runs it.
+/// The generator is named rather than taken from `StdRng`, whose algorithm may change between
+/// `rand` releases, so a seed reproduces its run across dependency updates.
+pub(crate) fn trial_rng(seed: u64, i: i64, stream: u8) -> ChaCha12Rng {
+    let mut key = [0u8; 32];
+    key[..8].copy_from_slice(&seed.to_le_bytes());
+    key[8..16].copy_from_slice(&i.to_le_bytes());
+    key[16] = stream;
+    ChaCha12Rng::from_seed(key)
+}
+
+/// A trial's place in an antithetic pair. With antithetic variates on, trials 1 and 2 form a
+/// pair, then 3 and 4, and so on; the second trial replays the first one's RNG streams with
+/// every uniform `u` turned into `1 - u` and every standard normal `z` into `-z`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PairRole {
+    Unpaired,
+    First,
+    Second,
+}
+
+impl PairRole {
+    fn of(antithetic: bool, i: i64) -> PairRole {
+        match (antithetic, i % 2) {
+            (false, _) => PairRole::Unpaired,
+            (true, 1) => PairRole::First,
+            (true, _) => PairRole::Second,
+        }
+    }
+
+    /// The trial whose RNG streams trial `i` draws from.
+    fn stream_trial(self, i: i64) -> i64 {
+        match self {
+            PairRole::Second => i - 1,
+            _ => i,
+        }
+    }
+
+    fn uniform(self, u: f64) -> f64 {
+        match self {
+            PairRole::Second => 1.0 - u,
+            _ => u,
+        }
+    }
+
+    fn normal(self, z: f64) -> f64 {
+        match self {
+            PairRole::Second => -z,
+            _ => z,
+        }
+    }
+}
+
+/// [`create_array`] for a trial playing `role`. Paired trials draw through the inverse CDF, as
+/// only a monotone map from the uniforms carries the pair's mirroring through to the values.
+fn create_paired_array(
+    params: &(Distributions, DistributionInputs),
+    n: usize,
+    rng: &mut ChaCha12Rng,
+    role: PairRole,
+) -> Result<Array1<f64>, DistributionError> {
+    if role == PairRole::Unpaired {
+        return create_array(params, n, rng);
+    }
+    let uniforms = Array1::<f64>::random_using(n, Open01, rng).mapv(|u| role.uniform(u));
+    create_array_from_uniforms(params, &uniforms)
+}
+
+/// Rounds of rejection sampling before [`create_array`] falls back to the inverse CDF.
+const REJECTION_ROUNDS: usize = 20;
+
+/// Bisection steps when locating a truncation bound's cumulative probability; enough to pin
+/// it down to double precision.
+const BOUND_BISECTION_STEPS: usize = 64;
+
+/// Draws `n` values of the chosen distribution, truncated to its bounds if it has any.
+///
+/// Truncated draws are made by rejection sampling. If the bounds reject so much that `n` values
+/// are not in after [`REJECTION_ROUNDS`], the rest come from the truncated inverse CDF.
 fn create_array(
     params: &(Distributions, DistributionInputs),
     n: usize,
+    rng: &mut ChaCha12Rng,
+) -> Result<Array1<f64>, DistributionError> {
+    let inputs = &params.1;
+    if !inputs.is_truncated() {
+        return create_untruncated_array(params, n, rng);
+    }
+    let (lower, upper) = bounds(inputs)?;
+    let mut values = Vec::with_capacity(n);
+    for _ in 0..REJECTION_ROUNDS {
+        if values.len() == n {
+            break;
+        }
+        let draws = create_untruncated_array(params, n - values.len(), rng)?;
+        values.extend(draws.iter().filter(|x| (lower..=upper).contains(*x)));
+    }
+    if values.len() < n {
+        let uniforms = Array1::random_using(n - values.len(), Uniform::new(0.0, 1.0), rng);
+        values.extend(create_array_from_uniforms(params, &uniforms)?);
+    }
+    Ok(Array1::from_vec(values))
+}
+
+/// The truncation bounds, open ends taken as infinite.
+fn bounds(inputs: &DistributionInputs) -> Result<(f64, f64), DistributionError> {
+    let lower = inputs.lower_bound.unwrap_or(f64::NEG_INFINITY);
+    let upper = inputs.upper_bound.unwrap_or(f64::INFINITY);
+    if lower <= upper {
+        Ok((lower, upper))
+    } else {
+        Err(DistributionError::Bounds)
+    }
+}
+
+fn create_untruncated_array(
+    params: &(Distributions, DistributionInputs),
+    n: usize,
+    rng: &mut ChaCha12Rng,
 ) -> Result<Array1<f64>, DistributionError> {
     let (distribution, input_strings) = params;
     let arr: Array1<f64> = match distribution {
         Distributions::Bernoulli => {
-            Array1::random(n, Bernoulli::new(input_strings.bernoulli_prob)?)
+            Array1::random_using(n, Bernoulli::new(input_strings.bernoulli_prob)?, rng)
                 .mapv(|x| if x { 1.0 } else { 0.0 })
         }
-        Distributions::Normal => Array1::random(
+        Distributions::Normal => Array1::random_using(
             n,
             Normal::new(input_strings.normal_mean, input_strings.normal_std)?,
+            rng,
         ),
-        Distributions::Pert => Array1::random(
+        Distributions::Pert => Array1::random_using(
             n,
             Pert::new(
                 input_strings.pert_min,
                 input_strings.pert_max,
                 input_strings.pert_mode,
             )?,
+            rng,
         ),
-        Distributions::Triangular => Array1::random(
+        Distributions::Triangular => Array1::random_using(
             n,
             Triangular::new(
                 input_strings.triangular_min,
                 input_strings.triangular_max,
                 input_strings.triangular_mode,
             )?,
+            rng,
         ),
-        Distributions::Uniform => Array1::random(
+        Distributions::Uniform => Array1::random_using(
             n,
             Uniform::new(input_strings.uniform_min, input_strings.uniform_max),
+            rng,
         ),
         Distributions::Constant => Array1::<f64>::ones(n) * input_strings.constant_val,
+        Distributions::LogNormal => Array1::random_using(
+            n,
+            LogNormal::new(input_strings.lognormal_mu, input_strings.lognormal_sigma)
+                .map_err(DistributionError::LogNormal)?,
+            rng,
+        ),
+        Distributions::Gamma => Array1::random_using(
+            n,
+            Gamma::new(input_strings.gamma_shape, input_strings.gamma_scale)?,
+            rng,
+        ),
+        Distributions::Beta => {
+            let (min, max) = beta_range(input_strings)?;
+            Array1::random_using(
+                n,
+                BetaDistribution::new(input_strings.beta_alpha, input_strings.beta_beta)?,
+                rng,
+            )
+            .mapv(|x| min + (max - min) * x)
+        }
+        Distributions::Weibull => Array1::random_using(
+            n,
+            Weibull::new(input_strings.weibull_scale, input_strings.weibull_shape)?,
+            rng,
+        ),
+        Distributions::Poisson => {
+            Array1::random_using(n, Poisson::new(input_strings.poisson_lambda)?, rng)
+        }
+        Distributions::NegativeBinomial => {
+            let (r, p) = negative_binomial_params(input_strings)?;
+            // A Poisson whose rate is Gamma distributed is Negative Binomial.
+            let gamma = Gamma::new(r, (1.0 - p) / p)?;
+            Array1::from_shape_fn(n, |_| {
+                let lambda = rng.sample(gamma);
+                Poisson::new(lambda).map_or(0.0, |poisson| rng.sample(poisson))
+            })
+        }
+        Distributions::Binomial => Array1::random_using(
+            n,
+            Binomial::new(input_strings.binomial_n, input_strings.binomial_p)?,
+            rng,
+        )
+        .mapv(|x| x as f64),
+        Distributions::DiscreteUniform => {
+            let (min, max) = discrete_uniform_range(input_strings)?;
+            Array1::random_using(n, Uniform::new_inclusive(min, max), rng).mapv(|x| x as f64)
+        }
+        Distributions::Empirical => {
+            let data = empirical_data(input_strings)?;
+            let smoothed = input_strings.empirical_smoothing;
+            Array1::from_shape_fn(n, |_| data.sample(smoothed, rng))
+        }
+        Distributions::Metalog => {
+            let metalog = metalog(input_strings)?;
+            Array1::from_shape_fn(n, |_| metalog.quantile(rng.sample(Open01)))
+        }
+        Distributions::DiscreteTable => {
+            let alias = WeightedAliasIndex::new(table_weights(input_strings))?;
+            let rows = &input_strings.discrete_table;
+            Array1::from_shape_fn(n, |_| rows[rng.sample(&alias)].value)
+        }
+    };
+    Ok(arr)
+}
+
+/// Maps correlated standard normals to the chosen distribution: `Φ` turns them into
+/// uniforms, which then go through the distribution's inverse CDF.
+fn create_array_from_normals(
+    params: &(Distributions, DistributionInputs),
+    normals: &Array1<f64>,
+) -> Result<Array1<f64>, DistributionError> {
+    let standard_normal = statrs::distribution::Normal::standard();
+    // Keep the uniforms inside (0, 1) so that unbounded inverse CDFs stay finite.
+    let uniforms = normals.mapv(|z| {
+        standard_normal
+            .cdf(z)
+            .clamp(f64::EPSILON, 1.0 - f64::EPSILON)
+    });
+    create_array_from_uniforms(params, &uniforms)
+}
+
+/// Inverse-CDF counterpart of [`create_array`]: maps each uniform in `(0, 1)` to the
+/// corresponding quantile of the chosen distribution.
+///
+/// A truncated distribution's quantiles are those of the untruncated one over the probability
+/// between the bounds, so the uniforms are first rescaled into that range.
+pub(crate) fn create_array_from_uniforms(
+    params: &(Distributions, DistributionInputs),
+    uniforms: &Array1<f64>,
+) -> Result<Array1<f64>, DistributionError> {
+    let inputs = &params.1;
+    if !inputs.is_truncated() {
+        return create_untruncated_array_from_uniforms(params, uniforms);
+    }
+    let (lower, upper) = bounds(inputs)?;
+    let below_lower = cumulative_probability(params, |x| x < lower)?;
+    let up_to_upper = cumulative_probability(params, |x| x <= upper)?;
+    if up_to_upper <= below_lower {
+        return Err(DistributionError::Truncation);
+    }
+    let uniforms = uniforms.mapv(|u| below_lower + u * (up_to_upper - below_lower));
+    // The bisection is exact only to rounding, so keep its slack from leaking past the bounds.
+    Ok(create_untruncated_array_from_uniforms(params, &uniforms)?.mapv(|x| x.clamp(lower, upper)))
+}
+
+/// Probability that a draw satisfies `below`, which must hold for every quantile up to some
+/// point and for none beyond it. Found by bisecting the quantile function, so it works for
+/// every distribution without a CDF of its own.
+fn cumulative_probability(
+    params: &(Distributions, DistributionInputs),
+    below: impl Fn(f64) -> bool,
+) -> Result<f64, DistributionError> {
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..BOUND_BISECTION_STEPS {
+        let mid = 0.5 * (low + high);
+        if below(create_untruncated_array_from_uniforms(params, &arr1(&[mid]))?[0]) {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    Ok(low)
+}
+
+fn create_untruncated_array_from_uniforms(
+    params: &(Distributions, DistributionInputs),
+    uniforms: &Array1<f64>,
+) -> Result<Array1<f64>, DistributionError> {
+    let (distribution, input_strings) = params;
+    let arr: Array1<f64> = match distribution {
+        Distributions::Bernoulli => {
+            let p = input_strings.bernoulli_prob;
+            Bernoulli::new(p)?;
+            uniforms.mapv(|u| if u > 1.0 - p { 1.0 } else { 0.0 })
+        }
+        Distributions::Normal => {
+            let (mean, std) = (input_strings.normal_mean, input_strings.normal_std);
+            Normal::new(mean, std)?;
+            let standard_normal = statrs::distribution::Normal::standard();
+            uniforms.mapv(|u| mean + std * standard_normal.inverse_cdf(u))
+        }
+        Distributions::Pert => {
+            let (min, max, mode) = (
+                input_strings.pert_min,
+                input_strings.pert_max,
+                input_strings.pert_mode,
+            );
+            Pert::new(min, max, mode)?;
+            // PERT is a Beta distribution with shape 4 rescaled to [min, max].
+            let range = max - min;
+            let beta = Beta::new(
+                1.0 + 4.0 * (mode - min) / range,
+                1.0 + 4.0 * (max - mode) / range,
+            )
+            .map_err(|e| DistributionError::InverseCdf(e.to_string()))?;
+            uniforms.mapv(|u| min + range * beta.inverse_cdf(u))
+        }
+        Distributions::Triangular => {
+            let (min, max, mode) = (
+                input_strings.triangular_min,
+                input_strings.triangular_max,
+                input_strings.triangular_mode,
+            );
+            Triangular::new(min, max, mode)?;
+            let range = max - min;
+            let mode_cdf = (mode - min) / range;
+            uniforms.mapv(|u| {
+                if u < mode_cdf {
+                    min + (u * range * (mode - min)).sqrt()
+                } else {
+                    max - ((1.0 - u) * range * (max - mode)).sqrt()
+                }
+            })
+        }
+        Distributions::Uniform => {
+            let (min, max) = (input_strings.uniform_min, input_strings.uniform_max);
+            uniforms.mapv(|u| min + u * (max - min))
+        }
+        Distributions::Constant => Array1::<f64>::ones(uniforms.len()) * input_strings.constant_val,
+        Distributions::LogNormal => {
+            let (mu, sigma) = (input_strings.lognormal_mu, input_strings.lognormal_sigma);
+            LogNormal::new(mu, sigma).map_err(DistributionError::LogNormal)?;
+            let standard_normal = statrs::distribution::Normal::standard();
+            uniforms.mapv(|u| (mu + sigma * standard_normal.inverse_cdf(u)).exp())
+        }
+        Distributions::Gamma => {
+            let (shape, scale) = (input_strings.gamma_shape, input_strings.gamma_scale);
+            Gamma::new(shape, scale)?;
+            let gamma = statrs::distribution::Gamma::new(shape, 1.0 / scale)
+                .map_err(|e| DistributionError::InverseCdf(e.to_string()))?;
+            uniforms.mapv(|u| gamma.inverse_cdf(u))
+        }
+        Distributions::Beta => {
+            let (alpha, beta) = (input_strings.beta_alpha, input_strings.beta_beta);
+            let (min, max) = beta_range(input_strings)?;
+            BetaDistribution::new(alpha, beta)?;
+            let beta =
+                Beta::new(alpha, beta).map_err(|e| DistributionError::InverseCdf(e.to_string()))?;
+            uniforms.mapv(|u| min + (max - min) * beta.inverse_cdf(u))
+        }
+        Distributions::Weibull => {
+            let (shape, scale) = (input_strings.weibull_shape, input_strings.weibull_scale);
+            Weibull::new(scale, shape)?;
+            uniforms.mapv(|u| scale * (-(1.0 - u).ln()).powf(1.0 / shape))
+        }
+        Distributions::Poisson => {
+            let lambda = input_strings.poisson_lambda;
+            Poisson::<f64>::new(lambda)?;
+            let poisson = statrs::distribution::Poisson::new(lambda)
+                .map_err(|e| DistributionError::InverseCdf(e.to_string()))?;
+            uniforms.mapv(|u| discrete_quantile(u, |k| poisson.cdf(k)))
+        }
+        Distributions::NegativeBinomial => {
+            let (r, p) = negative_binomial_params(input_strings)?;
+            let negative_binomial = statrs::distribution::NegativeBinomial::new(r, p)
+                .map_err(|e| DistributionError::InverseCdf(e.to_string()))?;
+            uniforms.mapv(|u| discrete_quantile(u, |k| negative_binomial.cdf(k)))
+        }
+        Distributions::Binomial => {
+            let (n, p) = (input_strings.binomial_n, input_strings.binomial_p);
+            Binomial::new(n, p)?;
+            let binomial = statrs::distribution::Binomial::new(p, n)
+                .map_err(|e| DistributionError::InverseCdf(e.to_string()))?;
+            uniforms.mapv(|u| discrete_quantile(u, |k| binomial.cdf(k)))
+        }
+        Distributions::DiscreteUniform => {
+            let (min, max) = discrete_uniform_range(input_strings)?;
+            let values = (max - min + 1) as f64;
+            uniforms.mapv(|u| (min as f64 + (u * values).floor()).min(max as f64))
+        }
+        Distributions::Empirical => {
+            let data = empirical_data(input_strings)?;
+            uniforms.mapv(|u| data.quantile(u, input_strings.empirical_smoothing))
+        }
+        Distributions::Metalog => {
+            let metalog = metalog(input_strings)?;
+            uniforms.mapv(|u| metalog.quantile(u))
+        }
+        Distributions::DiscreteTable => {
+            WeightedAliasIndex::new(table_weights(input_strings))?;
+            let mut rows = input_strings.discrete_table.clone();
+            rows.retain(|row| row.weight > 0.0);
+            rows.sort_by(|a, b| a.value.total_cmp(&b.value));
+            let total: f64 = rows.iter().map(|row| row.weight).sum();
+            uniforms.mapv(|u| {
+                let target = u * total;
+                let mut cumulative = 0.0;
+                rows.iter()
+                    .find(|row| {
+                        cumulative += row.weight;
+                        cumulative > target
+                    })
+                    .unwrap_or(&rows[rows.len() - 1])
+                    .value
+            })
+        }
     };
     Ok(arr)
 }
+
+/// Smallest count whose `cdf` reaches `u`, found by doubling then bisecting. statrs' own
+/// discrete inverse CDF can fail to bracket the answer and panic.
+fn discrete_quantile(u: f64, cdf: impl Fn(u64) -> f64) -> f64 {
+    let (mut low, mut high) = (0, 1);
+    while cdf(high) < u && high < 1 << 62 {
+        low = high;
+        high *= 2;
+    }
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if cdf(mid) >= u {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    high as f64
+}
+
+fn empirical_data(inputs: &DistributionInputs) -> Result<Arc<EmpiricalData>, DistributionError> {
+    Ok(empirical::load(
+        Path::new(&inputs.empirical_path),
+        &inputs.empirical_column,
+    )?)
+}
+
+fn metalog(inputs: &DistributionInputs) -> Result<Metalog, DistributionError> {
+    Ok(Metalog::new(
+        &inputs.metalog_points,
+        inputs.metalog_lower,
+        inputs.metalog_upper,
+    )?)
+}
+
+fn table_weights(inputs: &DistributionInputs) -> Vec<f64> {
+    inputs.discrete_table.iter().map(|row| row.weight).collect()
+}
+
+/// The Negative Binomial's `(r, p)`, checked because it is sampled through other distributions.
+fn negative_binomial_params(inputs: &DistributionInputs) -> Result<(f64, f64), DistributionError> {
+    let (r, p) = (inputs.negbinomial_r, inputs.negbinomial_p);
+    if r > 0.0 && p > 0.0 && p < 1.0 {
+        Ok((r, p))
+    } else {
+        Err(DistributionError::NegativeBinomial)
+    }
+}
+
+fn discrete_uniform_range(inputs: &DistributionInputs) -> Result<(i64, i64), DistributionError> {
+    if inputs.discrete_min <= inputs.discrete_max {
+        Ok((inputs.discrete_min, inputs.discrete_max))
+    } else {
+        Err(DistributionError::DiscreteUniform)
+    }
+}
+
+/// The interval a Beta variable is rescaled to.
+fn beta_range(inputs: &DistributionInputs) -> Result<(f64, f64), DistributionError> {
+    if inputs.beta_min < inputs.beta_max {
+        Ok((inputs.beta_min, inputs.beta_max))
+    } else {
+        Err(DistributionError::BetaRange)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convergence::ConvergenceTarget;
+    use crate::distributions::TableRow;
+    use statrs::distribution::Discrete;
+
+    type Params = (Distributions, DistributionInputs);
+
+    #[test]
+    fn discrete_table_draws_values_in_proportion_to_their_weights() {
+        let table = [(1.0, 40.0), (2.0, 35.0), (5.0, 25.0)];
+        let inputs = DistributionInputs {
+            discrete_table: table
+                .iter()
+                .map(|&(value, weight)| TableRow { value, weight })
+                .collect(),
+            ..Default::default()
+        };
+        let n = 100_000;
+        let draws = create_untruncated_array(
+            &(Distributions::DiscreteTable, inputs),
+            n,
+            &mut trial_rng(7, 1, 0),
+        )
+        .unwrap();
+        for (value, weight) in table {
+            let share = draws.iter().filter(|&&x| x == value).count() as f64 / n as f64;
+            assert!(
+                (share - weight / 100.0).abs() < 0.01,
+                "{value} drawn {share}"
+            );
+        }
+    }
+
+    fn normal(mean: f64, std: f64, lower: Option<f64>, upper: Option<f64>) -> Params {
+        (
+            Distributions::Normal,
+            DistributionInputs {
+                normal_mean: mean,
+                normal_std: std,
+                lower_bound: lower,
+                upper_bound: upper,
+                ..Default::default()
+            },
+        )
+    }
+
+    fn poisson(lambda: f64, lower: Option<f64>, upper: Option<f64>) -> Params {
+        (
+            Distributions::Poisson,
+            DistributionInputs {
+                poisson_lambda: lambda,
+                lower_bound: lower,
+                upper_bound: upper,
+                ..Default::default()
+            },
+        )
+    }
+
+    fn share(draws: &Array1<f64>, include: impl Fn(f64) -> bool) -> f64 {
+        draws.iter().filter(|&&x| include(x)).count() as f64 / draws.len() as f64
+    }
+
+    #[test]
+    fn bounded_draws_stay_within_their_bounds() {
+        let cases = [
+            normal(0.0, 1.0, Some(-0.5), Some(1.0)),
+            normal(0.0, 1.0, Some(1.5), None),
+            normal(0.0, 1.0, None, Some(-1.5)),
+            poisson(4.0, Some(2.0), Some(6.0)),
+            poisson(4.0, None, Some(3.0)),
+        ];
+        for params in cases {
+            let (lower, upper) = bounds(&params.1).unwrap();
+            let draws = create_array(&params, 10_000, &mut trial_rng(7, 1, 0)).unwrap();
+            assert_eq!(draws.len(), 10_000);
+            assert!(
+                draws.iter().all(|x| (lower..=upper).contains(x)),
+                "{params:?} drew outside its bounds"
+            );
+        }
+    }
+
+    #[test]
+    fn truncated_poisson_keeps_the_relative_probabilities_of_its_values() {
+        let params = poisson(4.0, Some(2.0), Some(6.0));
+        let untruncated = statrs::distribution::Poisson::new(4.0).unwrap();
+        let kept = untruncated.cdf(6) - untruncated.cdf(1);
+        let uniforms = Array1::from_shape_fn(10_000, |i| (i as f64 + 0.5) / 10_000.0);
+        let by_rejection = create_array(&params, 10_000, &mut trial_rng(7, 1, 0)).unwrap();
+        let by_inverse_cdf = create_array_from_uniforms(&params, &uniforms).unwrap();
+        for k in 2..=6 {
+            let expected = untruncated.pmf(k) / kept;
+            for draws in [&by_rejection, &by_inverse_cdf] {
+                let share = share(draws, |x| x == k as f64);
+                assert!((share - expected).abs() < 0.015, "P({k}) = {share}");
+            }
+        }
+    }
+
+    #[test]
+    fn inverse_cdf_fallback_follows_the_truncated_cdf() {
+        // So little of the normal lies beyond 5 that rejection sampling keeps next to nothing,
+        // leaving the draws to the inverse CDF.
+        let params = normal(0.0, 1.0, Some(5.0), None);
+        let draws = create_array(&params, 10_000, &mut trial_rng(7, 1, 0)).unwrap();
+        let standard = statrs::distribution::Normal::standard();
+        for x in [5.05, 5.1, 5.2, 5.5] {
+            let expected = 1.0 - standard.sf(x) / standard.sf(5.0);
+            let share = share(&draws, |draw| draw <= x);
+            assert!((share - expected).abs() < 0.02, "P(X <= {x}) = {share}");
+        }
+    }
+
+    #[test]
+    fn bounds_wider_than_the_support_change_nothing() {
+        let bounded = poisson(4.0, Some(-10.0), Some(1000.0));
+        let uniforms = Array1::from_shape_fn(1000, |i| (i as f64 + 0.5) / 1000.0);
+        let unbounded = poisson(4.0, None, None);
+        assert_eq!(
+            create_array_from_uniforms(&bounded, &uniforms).unwrap(),
+            create_array_from_uniforms(&unbounded, &uniforms).unwrap()
+        );
+    }
+
+    #[test]
+    fn bounds_outside_the_support_are_rejected() {
+        let params = poisson(4.0, None, Some(-1.0));
+        let uniforms = arr1(&[0.5]);
+        assert!(matches!(
+            create_array_from_uniforms(&params, &uniforms),
+            Err(DistributionError::Truncation)
+        ));
+        assert!(matches!(
+            create_array(&params, 100, &mut trial_rng(7, 1, 0)),
+            Err(DistributionError::Truncation)
+        ));
+    }
+
+    #[test]
+    fn antithetic_partners_draw_mirrored_uniforms() {
+        // A unit uniform's inverse CDF is the identity, so its draws are the uniforms.
+        let params = (
+            Distributions::Uniform,
+            DistributionInputs {
+                uniform_min: 0.0,
+                uniform_max: 1.0,
+                ..Default::default()
+            },
+        );
+        let (first, second) = (PairRole::of(true, 1), PairRole::of(true, 2));
+        assert_eq!((first, second), (PairRole::First, PairRole::Second));
+        assert_eq!(second.stream_trial(2), 1);
+        let draw = |role: PairRole, i: i64| {
+            create_paired_array(
+                &params,
+                1000,
+                &mut trial_rng(7, role.stream_trial(i), 0),
+                role,
+            )
+            .unwrap()
+        };
+        let (u, mirrored) = (draw(first, 1), draw(second, 2));
+        for (u, mirrored) in u.iter().zip(&mirrored) {
+            assert!((u + mirrored - 1.0).abs() < 1e-12, "{u} and {mirrored}");
+        }
+    }
+
+    /// Runs `config` to a scratch parquet and returns how its convergence target fared.
+    fn converge(config: &SimulationConfig, name: &str) -> ConvergenceReport {
+        let path =
+            std::env::temp_dir().join(format!("sandhiya_{name}_{}.parquet", std::process::id()));
+        let summary = start_simulation(config, &path, None, &CancellationToken::default());
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(path.with_file_name(format!(
+            "{}_summary.csv",
+            path.file_stem().unwrap().to_string_lossy()
+        )));
+        summary.unwrap().convergence.unwrap()
+    }
+
+    #[test]
+    fn convergence_stops_once_the_target_is_met_or_at_the_cap() {
+        let mut config = SimulationConfig {
+            commission_rate: 5.0,
+            num_months: 1,
+            trials: 2 * TRIALS_PER_BATCH as usize,
+            ..Default::default()
+        };
+        config.convergence = Some(ConvergenceTarget {
+            target: 0.1,
+            ..Default::default()
+        });
+        let met = converge(&config, "met");
+        assert!(met.converged);
+        assert_eq!(
+            met.trials, TRIALS_PER_BATCH as usize,
+            "stopped after the first batch"
+        );
+
+        config.convergence = Some(ConvergenceTarget {
+            target: 1e-9,
+            ..Default::default()
+        });
+        let capped = converge(&config, "capped");
+        assert!(!capped.converged);
+        assert_eq!(capped.trials, config.trials);
+    }
+}
@@ -1,5 +1,33 @@
+use crate::errors::SimulationError;
+use crate::sampling::StrategyComparison;
+use crate::sensitivity::SensitivityAnalysis;
+use crate::sobol::SobolAnalysis;
+use crate::summary::SimulationSummary;
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
 pub enum SimulationMessage {
     Progress(f32),
-    Error(String),
-    Success(String),
+    Error(SimulationError),
+    Success(SimulationSummary),
+    Sensitivity(SensitivityAnalysis),
+    Sobol(SobolAnalysis),
+    Sampling(StrategyComparison),
+    Cancelled,
+}
+
+/// Shared flag used to ask a running simulation to stop. Clones refer to the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
 }
@@ -0,0 +1,251 @@
+use crate::distributions::MetalogPoint;
+use crate::errors::MetalogError;
+
+/// Evenly spaced probabilities at which the quantile function is checked to be increasing.
+const FEASIBILITY_GRID_POINTS: usize = 1000;
+
+/// Tail probabilities, down to `10^-n`, checked as well, since that is where infeasibility
+/// usually shows first.
+const FEASIBILITY_TAIL_DIGITS: i32 = 12;
+
+/// A Metalog distribution (Keelin, 2016): a quantile function that is a series in the
+/// probability `y` and its logit, with one term per quantile it is fitted to, so it passes
+/// exactly through every one of them.
+///
+/// A lower and/or upper limit makes it semi-bounded or bounded by fitting the series to
+/// `ln(x - lower)`, `-ln(upper - x)` or `ln((x - lower) / (upper - x))` instead of `x`.
+#[derive(Debug, Clone)]
+pub struct Metalog {
+    coefficients: Vec<f64>,
+    lower: Option<f64>,
+    upper: Option<f64>,
+}
+
+impl Metalog {
+    /// Fits a Metalog through `points`, with as many terms as there are points.
+    ///
+    /// Fails unless there are at least two points with distinct probabilities in (0, 1), values
+    /// that increase with probability and lie strictly within the limits, and a quantile
+    /// function through them that increases everywhere.
+    pub fn new(
+        points: &[MetalogPoint],
+        lower: Option<f64>,
+        upper: Option<f64>,
+    ) -> Result<Metalog, MetalogError> {
+        if points.len() < 2 {
+            return Err(MetalogError::TooFewPoints);
+        }
+        if let (Some(lower), Some(upper)) = (lower, upper)
+            && lower >= upper
+        {
+            return Err(MetalogError::Limits);
+        }
+        let mut points = points.to_vec();
+        points.sort_by(|a, b| a.probability.total_cmp(&b.probability));
+        if points
+            .iter()
+            .any(|point| point.probability <= 0.0 || point.probability >= 1.0)
+        {
+            return Err(MetalogError::Probability);
+        }
+        if points.windows(2).any(|pair| {
+            pair[0].probability >= pair[1].probability || pair[0].value >= pair[1].value
+        }) {
+            return Err(MetalogError::NotIncreasing);
+        }
+        if points.iter().any(|point| {
+            lower.is_some_and(|lower| point.value <= lower)
+                || upper.is_some_and(|upper| point.value >= upper)
+        }) {
+            return Err(MetalogError::OutsideLimits);
+        }
+
+        let terms = points.len();
+        let rows: Vec<Vec<f64>> = points
+            .iter()
+            .map(|point| (0..terms).map(|j| basis(j, point.probability)).collect())
+            .collect();
+        let targets: Vec<f64> = points
+            .iter()
+            .map(|point| transform(point.value, lower, upper))
+            .collect();
+        let coefficients = solve_linear(rows, targets).ok_or(MetalogError::Infeasible)?;
+        let metalog = Metalog {
+            coefficients,
+            lower,
+            upper,
+        };
+        if metalog.is_feasible() {
+            Ok(metalog)
+        } else {
+            Err(MetalogError::Infeasible)
+        }
+    }
+
+    /// The value with probability `y` of lying below it.
+    pub fn quantile(&self, y: f64) -> f64 {
+        let m: f64 = self
+            .coefficients
+            .iter()
+            .enumerate()
+            .map(|(j, a)| a * basis(j, y))
+            .sum();
+        match (self.lower, self.upper) {
+            (None, None) => m,
+            (Some(lower), None) => lower + m.exp(),
+            (None, Some(upper)) => upper - (-m).exp(),
+            (Some(lower), Some(upper)) => {
+                // Written so that large |m| does not overflow to inf / inf.
+                if m > 0.0 {
+                    (lower * (-m).exp() + upper) / (1.0 + (-m).exp())
+                } else {
+                    (lower + upper * m.exp()) / (1.0 + m.exp())
+                }
+            }
+        }
+    }
+
+    /// True if the series increases with `y` all the way across (0, 1). The limits' transforms
+    /// are increasing, so this is the same as the quantile function increasing.
+    fn is_feasible(&self) -> bool {
+        let tails = (3..=FEASIBILITY_TAIL_DIGITS).map(|n| 10f64.powi(-n));
+        let mut grid = (1..FEASIBILITY_GRID_POINTS)
+            .map(|i| i as f64 / FEASIBILITY_GRID_POINTS as f64)
+            .chain(tails.clone())
+            .chain(tails.map(|tail| 1.0 - tail));
+        grid.all(|y| {
+            let slope: f64 = self
+                .coefficients
+                .iter()
+                .enumerate()
+                .map(|(j, a)| a * basis_slope(j, y))
+                .sum();
+            slope > 0.0
+        })
+    }
+}
+
+/// The powers `(p, q)` of term `j` (counting from 0), which is `(y - 0.5)^p * logit(y)^q`.
+fn powers(j: usize) -> (i32, i32) {
+    match j {
+        0 => (0, 0),
+        1 => (0, 1),
+        2 => (1, 1),
+        3 => (1, 0),
+        // From the fifth term on, the powers of (y - 0.5) go up every other term, without
+        // and then with the logit.
+        _ if j.is_multiple_of(2) => (j as i32 / 2, 0),
+        _ => ((j as i32 - 1) / 2, 1),
+    }
+}
+
+fn basis(j: usize, y: f64) -> f64 {
+    let (p, q) = powers(j);
+    (y - 0.5).powi(p) * logit(y).powi(q)
+}
+
+/// The derivative of [`basis`] with respect to `y`.
+fn basis_slope(j: usize, y: f64) -> f64 {
+    let (p, q) = powers(j);
+    let centred = y - 0.5;
+    let from_power = if p > 0 {
+        p as f64 * centred.powi(p - 1) * logit(y).powi(q)
+    } else {
+        0.0
+    };
+    let from_logit = if q > 0 {
+        centred.powi(p) / (y * (1.0 - y))
+    } else {
+        0.0
+    };
+    from_power + from_logit
+}
+
+fn logit(y: f64) -> f64 {
+    (y / (1.0 - y)).ln()
+}
+
+/// Maps a value onto the unbounded scale the series is fitted on.
+fn transform(x: f64, lower: Option<f64>, upper: Option<f64>) -> f64 {
+    match (lower, upper) {
+        (None, None) => x,
+        (Some(lower), None) => (x - lower).ln(),
+        (None, Some(upper)) => -(upper - x).ln(),
+        (Some(lower), Some(upper)) => ((x - lower) / (upper - x)).ln(),
+    }
+}
+
+/// Solves the square system `rows · a = targets` by Gaussian elimination with partial
+/// pivoting. None if it is singular.
+fn solve_linear(mut rows: Vec<Vec<f64>>, mut targets: Vec<f64>) -> Option<Vec<f64>> {
+    let n = targets.len();
+    for column in 0..n {
+        let pivot =
+            (column..n).max_by(|&a, &b| rows[a][column].abs().total_cmp(&rows[b][column].abs()))?;
+        if rows[pivot][column].abs() < f64::EPSILON {
+            return None;
+        }
+        rows.swap(column, pivot);
+        targets.swap(column, pivot);
+        let (above, below) = rows.split_at_mut(column + 1);
+        let pivot_row = &above[column];
+        let pivot_target = targets[column];
+        for (row, target) in below.iter_mut().zip(&mut targets[column + 1..]) {
+            let factor = row[column] / pivot_row[column];
+            for (x, pivot_x) in row[column..].iter_mut().zip(&pivot_row[column..]) {
+                *x -= factor * pivot_x;
+            }
+            *target -= factor * pivot_target;
+        }
+    }
+    let mut solution = vec![0.0; n];
+    for row in (0..n).rev() {
+        let known: f64 = (row + 1..n).map(|k| rows[row][k] * solution[k]).sum();
+        solution[row] = (targets[row] - known) / rows[row][row];
+    }
+    Some(solution)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(pairs: &[(f64, f64)]) -> Vec<MetalogPoint> {
+        pairs
+            .iter()
+            .map(|&(probability, value)| MetalogPoint { probability, value })
+            .collect()
+    }
+
+    #[test]
+    fn quantiles_pass_through_the_fitted_points() {
+        let expert = points(&[
+            (0.05, 12.0),
+            (0.25, 18.0),
+            (0.5, 22.0),
+            (0.75, 27.0),
+            (0.95, 36.0),
+        ]);
+        for (lower, upper) in [(None, None), (Some(0.0), None), (Some(0.0), Some(60.0))] {
+            let metalog = Metalog::new(&expert, lower, upper).unwrap();
+            for point in &expert {
+                let value = metalog.quantile(point.probability);
+                assert!(
+                    (value - point.value).abs() < 1e-9,
+                    "P{} is {value} with limits {lower:?}-{upper:?}",
+                    point.probability * 100.0
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn quantiles_that_turn_back_are_infeasible() {
+        // Nearly equal middle quantiles between wide tails force the series to fold over.
+        let expert = points(&[(0.1, 0.0), (0.45, 10.0), (0.55, 10.001), (0.9, 20.0)]);
+        assert!(matches!(
+            Metalog::new(&expert, None, None),
+            Err(MetalogError::Infeasible)
+        ));
+    }
+}
@@ -0,0 +1,97 @@
+use crate::charts;
+use crate::fit_panel;
+use eframe::egui;
+use sandhiya::correlation::CorrelatedInput;
+use sandhiya::distributions::{DistributionInputs, Distributions};
+use sandhiya::quantiles::{self, Implied};
+
+/// Sets one model input's distribution from a P10, P50 and P90 rather than its parameters.
+pub struct QuantileEntry {
+    pub input: CorrelatedInput,
+    distribution: Distributions,
+    /// The input's parameters when the entry was opened; the solution keeps their bounds.
+    base: DistributionInputs,
+    targets: [f64; 3],
+    /// The parameters solved for the current targets and what they imply, or why there are none.
+    solution: Result<(DistributionInputs, Implied), String>,
+}
+
+impl QuantileEntry {
+    /// Starts from the current distribution's own P10, P50 and P90, if it has valid ones.
+    pub fn new(
+        input: CorrelatedInput,
+        distribution: Distributions,
+        inputs: &DistributionInputs,
+    ) -> Self {
+        let targets = quantiles::implied(distribution, inputs)
+            .map(|implied| implied.quantiles)
+            .unwrap_or([0.0, 1.0, 2.0]);
+        let mut entry = Self {
+            input,
+            distribution,
+            base: inputs.clone(),
+            targets,
+            solution: Err(String::new()),
+        };
+        entry.solve();
+        entry
+    }
+
+    fn solve(&mut self) {
+        self.solution = quantiles::solve(self.distribution, self.targets, &self.base)
+            .map_err(|e| e.to_string())
+            .and_then(|inputs| {
+                let implied =
+                    quantiles::implied(self.distribution, &inputs).map_err(|e| e.to_string())?;
+                Ok((inputs, implied))
+            });
+    }
+
+    /// Renders the quantile editors and the solved distribution. Returns its parameters once
+    /// the user accepts them.
+    pub fn show(&mut self, ui: &mut egui::Ui) -> Option<DistributionInputs> {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            for (label, target) in ["P10", "P50", "P90"].iter().zip(&mut self.targets) {
+                ui.label(*label);
+                let response = ui.add(egui::DragValue::new(target).speed(0.1));
+                // Solving can take a while, so a drag is solved once, when it ends.
+                changed |= response.drag_stopped() || (response.changed() && !response.dragged());
+            }
+        });
+        if changed {
+            self.solve();
+        }
+
+        let (inputs, implied) = match &self.solution {
+            Ok(solution) => solution,
+            Err(e) => {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+                return None;
+            }
+        };
+        ui.label(format!(
+            "{}: {}",
+            self.distribution,
+            fit_panel::describe(self.distribution, inputs)
+        ));
+        egui::Grid::new("implied_quantiles").show(ui, |ui| {
+            ui.label("");
+            for header in ["P10", "P50", "P90"] {
+                ui.label(header);
+            }
+            ui.end_row();
+            ui.label("Implied");
+            for quantile in implied.quantiles {
+                ui.label(format!("{:.2}", quantile));
+            }
+            ui.end_row();
+        });
+        ui.label(format!(
+            "Implied mean {:.2}, standard deviation {:.2}",
+            implied.mean, implied.std
+        ));
+        charts::show_data_histogram(ui, ui.id().with("implied_density"), &implied.grid);
+        ui.button("Accept").clicked().then(|| inputs.clone())
+    }
+}
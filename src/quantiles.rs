@@ -0,0 +1,465 @@
+use crate::distributions::{DistributionInputs, Distributions, MetalogPoint};
+use crate::errors::{DistributionError, QuantileError};
+use crate::mcs::create_array_from_uniforms;
+use ndarray::{Array1, arr1};
+
+/// Probabilities of the P10, P50 and P90 a distribution is set from.
+pub const QUANTILE_PROBABILITIES: [f64; 3] = [0.10, 0.50, 0.90];
+
+/// Evenly spaced quantiles the implied mean, standard deviation and density are taken from.
+const IMPLIED_GRID_POINTS: usize = 1000;
+
+/// P90 of the standard normal.
+const Z90: f64 = 1.281_551_565_544_600_4;
+
+const SOLVER_ITERATIONS: usize = 2000;
+
+/// The solver stops once its best and worst points are this close in cost, i.e. when the
+/// quantiles move by well under a thousandth of the P10-P90 spread.
+const SOLVER_TOLERANCE: f64 = 1e-10;
+
+/// Furthest, as a share of the P10-P90 spread, a solution's quantile may land from its target.
+const MAX_RESIDUAL: f64 = 0.1;
+
+/// What a distribution's parameters imply, to check them against the quantiles asked for.
+#[derive(Debug, Clone)]
+pub struct Implied {
+    /// The distribution's P10, P50 and P90.
+    pub quantiles: [f64; 3],
+    pub mean: f64,
+    pub std: f64,
+    /// Evenly spaced quantiles, sorted, so their histogram traces the density.
+    pub grid: Vec<f64>,
+}
+
+/// True for distributions that can be set from a P10, P50 and P90.
+pub fn supports(distribution: Distributions) -> bool {
+    !matches!(
+        distribution,
+        Distributions::Bernoulli
+            | Distributions::Constant
+            | Distributions::DiscreteTable
+            | Distributions::Empirical
+    )
+}
+
+/// Parameters of `distribution` whose P10, P50 and P90 come closest to `targets`, in least
+/// squares relative to the P10-P90 spread.
+///
+/// A Metalog takes the targets as its quantiles, and three-parameter distributions (Triangular,
+/// Pert) can usually match all three. Two-parameter ones match them as well as their shape
+/// allows, and fail if that leaves a quantile more than a tenth of the P10-P90 spread off.
+/// The Beta keeps the range of `base` if it spans the targets, or else reaches one P10-P90
+/// spread beyond them. The rest of `base`, such as truncation bounds, is kept and taken into
+/// account.
+pub fn solve(
+    distribution: Distributions,
+    targets: [f64; 3],
+    base: &DistributionInputs,
+) -> Result<DistributionInputs, QuantileError> {
+    let [p10, p50, p90] = targets;
+    if p10 >= p50 || p50 >= p90 {
+        return Err(QuantileError::NotIncreasing);
+    }
+    let positive_only = matches!(
+        distribution,
+        Distributions::LogNormal | Distributions::Gamma | Distributions::Weibull
+    );
+    if positive_only && p10 <= 0.0 {
+        return Err(QuantileError::NotPositive(distribution));
+    }
+    if distribution == Distributions::Metalog {
+        // A Metalog is given by its quantiles, so these are taken as they are.
+        let mut inputs = base.clone();
+        inputs.metalog_points = QUANTILE_PROBABILITIES
+            .iter()
+            .zip(targets)
+            .map(|(&probability, value)| MetalogPoint { probability, value })
+            .collect();
+        quantiles(distribution, &inputs).map_err(|_| QuantileError::NoSolution(distribution))?;
+        return Ok(inputs);
+    }
+    let mut base = base.clone();
+    if distribution == Distributions::Beta && !(base.beta_min < p10 && p90 < base.beta_max) {
+        (base.beta_min, base.beta_max) = (p10 - (p90 - p10), p90 + (p90 - p10));
+    }
+    let base = &base;
+    let start = start(distribution, targets, base)?;
+    let steps: Vec<f64> = start.iter().map(|x| 0.1 * x.abs().max(p90 - p10)).collect();
+    let cost = |params: &[f64]| {
+        let Some(inputs) = with_params(distribution, params, base) else {
+            return f64::INFINITY;
+        };
+        match quantiles(distribution, &inputs) {
+            Ok(quantiles) => quantiles
+                .iter()
+                .zip(targets)
+                .map(|(quantile, target)| ((quantile - target) / (p90 - p10)).powi(2))
+                .sum(),
+            Err(_) => f64::INFINITY,
+        }
+    };
+    let (best, best_cost) = nelder_mead(cost, start, &steps);
+    // The cost is a sum of squared misses, so this bounds each of them by `MAX_RESIDUAL`.
+    if best_cost.is_nan() || best_cost > MAX_RESIDUAL.powi(2) {
+        return Err(QuantileError::NoSolution(distribution));
+    }
+    with_params(distribution, &best, base).ok_or(QuantileError::NoSolution(distribution))
+}
+
+/// The P10, P50 and P90 of the chosen distribution, its mean and standard deviation, and a
+/// grid of its quantiles to preview its density with.
+pub fn implied(
+    distribution: Distributions,
+    inputs: &DistributionInputs,
+) -> Result<Implied, DistributionError> {
+    let params = (distribution, inputs.clone());
+    let uniforms = Array1::from_iter(
+        (0..IMPLIED_GRID_POINTS).map(|i| (i as f64 + 0.5) / IMPLIED_GRID_POINTS as f64),
+    );
+    let grid = create_array_from_uniforms(&params, &uniforms)?;
+    let mean = grid.mean().unwrap_or(f64::NAN);
+    let std = grid.std(0.0);
+    Ok(Implied {
+        quantiles: quantiles(distribution, inputs)?,
+        mean,
+        std,
+        grid: grid.to_vec(),
+    })
+}
+
+fn quantiles(
+    distribution: Distributions,
+    inputs: &DistributionInputs,
+) -> Result<[f64; 3], DistributionError> {
+    let params = (distribution, inputs.clone());
+    let quantiles = create_array_from_uniforms(&params, &arr1(&QUANTILE_PROBABILITIES))?;
+    Ok([quantiles[0], quantiles[1], quantiles[2]])
+}
+
+/// Where the solver starts: the parameters matching a mean and standard deviation read off the
+/// quantiles (Swanson's rule and a normal spread), or the quantiles directly where that is
+/// simpler.
+fn start(
+    distribution: Distributions,
+    [p10, p50, p90]: [f64; 3],
+    base: &DistributionInputs,
+) -> Result<Vec<f64>, QuantileError> {
+    let spread = p90 - p10;
+    let mean = 0.3 * p10 + 0.4 * p50 + 0.3 * p90;
+    let std = spread / (2.0 * Z90);
+    let start = match distribution {
+        Distributions::Normal => vec![mean, std],
+        Distributions::Uniform => vec![p10 - 0.125 * spread, p90 + 0.125 * spread],
+        Distributions::Triangular | Distributions::Pert => {
+            vec![p10 - 0.5 * spread, p50, p90 + 0.5 * spread]
+        }
+        Distributions::LogNormal => vec![p50.ln(), (p90 / p10).ln() / (2.0 * Z90)],
+        Distributions::Gamma => vec![(mean / std).powi(2), std * std / mean],
+        Distributions::Beta => {
+            let (min, max) = (base.beta_min, base.beta_max);
+            let (mean, variance) = ((mean - min) / (max - min), (std / (max - min)).powi(2));
+            let concentration = (mean * (1.0 - mean) / variance - 1.0).max(1.0);
+            vec![mean * concentration, (1.0 - mean) * concentration]
+        }
+        Distributions::Weibull => {
+            // Exact through P10 and P90.
+            let shape = ((0.1f64.ln() / 0.9f64.ln()).ln()) / (p90 / p10).ln();
+            vec![shape, p90 / (-(0.1f64.ln())).powf(1.0 / shape)]
+        }
+        Distributions::Poisson => vec![mean.max(std * std)],
+        Distributions::NegativeBinomial => {
+            let variance = (std * std).max(1.1 * mean);
+            vec![mean * mean / (variance - mean), mean / variance]
+        }
+        Distributions::Binomial => {
+            let p = (1.0 - std * std / mean).clamp(0.05, 0.95);
+            vec![(mean / p).ceil(), p]
+        }
+        Distributions::DiscreteUniform => {
+            vec![
+                (p10 - 0.125 * spread).floor(),
+                (p90 + 0.125 * spread).ceil(),
+            ]
+        }
+        Distributions::Bernoulli
+        | Distributions::Constant
+        | Distributions::DiscreteTable
+        | Distributions::Empirical
+        | Distributions::Metalog => return Err(QuantileError::Unsupported(distribution)),
+    };
+    Ok(start)
+}
+
+/// `base` with the parameters the solver works on set to `params`, or None if they are out of
+/// range.
+fn with_params(
+    distribution: Distributions,
+    params: &[f64],
+    base: &DistributionInputs,
+) -> Option<DistributionInputs> {
+    let mut inputs = base.clone();
+    let positive = |x: f64| (x > 0.0).then_some(x);
+    match distribution {
+        Distributions::Normal => {
+            inputs.normal_mean = params[0];
+            inputs.normal_std = positive(params[1])?;
+        }
+        Distributions::Uniform => {
+            (inputs.uniform_min, inputs.uniform_max) = (params[0], params[1]);
+            positive(params[1] - params[0])?;
+        }
+        Distributions::Triangular => {
+            (
+                inputs.triangular_min,
+                inputs.triangular_mode,
+                inputs.triangular_max,
+            ) = (params[0], params[1], params[2]);
+        }
+        Distributions::Pert => {
+            (inputs.pert_min, inputs.pert_mode, inputs.pert_max) =
+                (params[0], params[1], params[2]);
+        }
+        Distributions::LogNormal => {
+            inputs.lognormal_mu = params[0];
+            inputs.lognormal_sigma = positive(params[1])?;
+        }
+        Distributions::Gamma => {
+            inputs.gamma_shape = positive(params[0])?;
+            inputs.gamma_scale = positive(params[1])?;
+        }
+        Distributions::Beta => {
+            inputs.beta_alpha = positive(params[0])?;
+            inputs.beta_beta = positive(params[1])?;
+        }
+        Distributions::Weibull => {
+            inputs.weibull_shape = positive(params[0])?;
+            inputs.weibull_scale = positive(params[1])?;
+        }
+        Distributions::Poisson => inputs.poisson_lambda = positive(params[0])?,
+        Distributions::NegativeBinomial => {
+            inputs.negbinomial_r = positive(params[0])?;
+            inputs.negbinomial_p = params[1];
+        }
+        Distributions::Binomial => {
+            inputs.binomial_n = positive(params[0].round())? as u64;
+            inputs.binomial_p = params[1];
+        }
+        Distributions::DiscreteUniform => {
+            (inputs.discrete_min, inputs.discrete_max) =
+                (params[0].round() as i64, params[1].round() as i64);
+        }
+        Distributions::Bernoulli
+        | Distributions::Constant
+        | Distributions::DiscreteTable
+        | Distributions::Empirical
+        | Distributions::Metalog => return None,
+    }
+    Some(inputs)
+}
+
+/// Minimises `cost` by the Nelder-Mead simplex method, starting from `start` with a first step
+/// of `steps` along each axis. Infinite costs mark points to stay away from.
+fn nelder_mead(cost: impl Fn(&[f64]) -> f64, start: Vec<f64>, steps: &[f64]) -> (Vec<f64>, f64) {
+    let mut simplex: Vec<(Vec<f64>, f64)> = vec![(start.clone(), cost(&start))];
+    for (axis, step) in steps.iter().enumerate() {
+        let mut point = start.clone();
+        point[axis] += step;
+        let value = cost(&point);
+        simplex.push((point, value));
+    }
+    let n = start.len();
+    // Moves the worst point through the centroid of the others, scaled by `t`.
+    let towards = |centroid: &[f64], worst: &[f64], t: f64| -> Vec<f64> {
+        centroid
+            .iter()
+            .zip(worst)
+            .map(|(c, w)| c + t * (c - w))
+            .collect()
+    };
+    for _ in 0..SOLVER_ITERATIONS {
+        simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+        if simplex[n].1 - simplex[0].1 <= SOLVER_TOLERANCE {
+            break;
+        }
+        let centroid: Vec<f64> = (0..n)
+            .map(|axis| {
+                simplex[..n]
+                    .iter()
+                    .map(|(point, _)| point[axis])
+                    .sum::<f64>()
+                    / n as f64
+            })
+            .collect();
+        let worst = simplex[n].0.clone();
+        let reflected = towards(&centroid, &worst, 1.0);
+        let reflected_cost = cost(&reflected);
+        if reflected_cost < simplex[0].1 {
+            let expanded = towards(&centroid, &worst, 2.0);
+            let expanded_cost = cost(&expanded);
+            simplex[n] = if expanded_cost < reflected_cost {
+                (expanded, expanded_cost)
+            } else {
+                (reflected, reflected_cost)
+            };
+        } else if reflected_cost < simplex[n - 1].1 {
+            simplex[n] = (reflected, reflected_cost);
+        } else {
+            let contracted = towards(&centroid, &worst, -0.5);
+            let contracted_cost = cost(&contracted);
+            if contracted_cost < simplex[n].1 {
+                simplex[n] = (contracted, contracted_cost);
+            } else {
+                let best = simplex[0].0.clone();
+                for (point, value) in simplex.iter_mut().skip(1) {
+                    *point = best
+                        .iter()
+                        .zip(&*point)
+                        .map(|(b, x)| b + 0.5 * (x - b))
+                        .collect();
+                    *value = cost(point);
+                }
+            }
+        }
+    }
+    simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+    simplex.swap_remove(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use strum::IntoEnumIterator;
+
+    fn inputs(set: impl FnOnce(&mut DistributionInputs)) -> DistributionInputs {
+        let mut inputs = DistributionInputs::default();
+        set(&mut inputs);
+        inputs
+    }
+
+    /// Solves for the P10, P50 and P90 of `known` and checks the solution implies them again.
+    fn assert_round_trip(distribution: Distributions, known: DistributionInputs) {
+        let targets = implied(distribution, &known).unwrap().quantiles;
+        let solved =
+            solve(distribution, targets, &known).unwrap_or_else(|e| panic!("{distribution}: {e}"));
+        let quantiles = implied(distribution, &solved).unwrap().quantiles;
+        let spread = targets[2] - targets[0];
+        for (quantile, target) in quantiles.iter().zip(targets) {
+            assert!(
+                (quantile - target).abs() <= 1e-3 * spread,
+                "{distribution}: {quantiles:?} for {targets:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn solutions_reproduce_the_quantiles_of_every_family() {
+        use Distributions::*;
+        let cases = [
+            (
+                Normal,
+                inputs(|i| (i.normal_mean, i.normal_std) = (20.0, 3.0)),
+            ),
+            (
+                Uniform,
+                inputs(|i| (i.uniform_min, i.uniform_max) = (5.0, 15.0)),
+            ),
+            (
+                Triangular,
+                inputs(|i| {
+                    (i.triangular_min, i.triangular_mode, i.triangular_max) = (0.0, 2.0, 10.0)
+                }),
+            ),
+            (
+                Pert,
+                inputs(|i| (i.pert_min, i.pert_mode, i.pert_max) = (18.0, 20.0, 26.0)),
+            ),
+            (
+                LogNormal,
+                inputs(|i| (i.lognormal_mu, i.lognormal_sigma) = (3.0, 0.4)),
+            ),
+            (
+                Gamma,
+                inputs(|i| (i.gamma_shape, i.gamma_scale) = (4.0, 2.5)),
+            ),
+            (
+                Beta,
+                inputs(|i| {
+                    (i.beta_alpha, i.beta_beta) = (2.0, 5.0);
+                    (i.beta_min, i.beta_max) = (0.0, 100.0);
+                }),
+            ),
+            (
+                Weibull,
+                inputs(|i| (i.weibull_shape, i.weibull_scale) = (1.5, 30.0)),
+            ),
+            (Poisson, inputs(|i| i.poisson_lambda = 25.0)),
+            (
+                NegativeBinomial,
+                inputs(|i| (i.negbinomial_r, i.negbinomial_p) = (16.0, 0.57)),
+            ),
+            (
+                Binomial,
+                inputs(|i| (i.binomial_n, i.binomial_p) = (40, 0.3)),
+            ),
+            (
+                DiscreteUniform,
+                inputs(|i| (i.discrete_min, i.discrete_max) = (20, 26)),
+            ),
+        ];
+        for distribution in Distributions::iter().filter(|&d| supports(d) && d != Metalog) {
+            assert!(
+                cases.iter().any(|(case, _)| *case == distribution),
+                "{distribution} is not covered"
+            );
+        }
+        for (distribution, known) in cases {
+            assert_round_trip(distribution, known);
+        }
+    }
+
+    #[test]
+    fn metalog_takes_the_quantiles_as_given() {
+        let solved = solve(
+            Distributions::Metalog,
+            [18.0, 22.0, 31.0],
+            &Default::default(),
+        )
+        .unwrap();
+        let quantiles = implied(Distributions::Metalog, &solved).unwrap().quantiles;
+        for (quantile, target) in quantiles.iter().zip([18.0, 22.0, 31.0]) {
+            assert!((quantile - target).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn solutions_keep_truncation_bounds() {
+        assert_round_trip(
+            Distributions::Normal,
+            inputs(|i| {
+                (i.normal_mean, i.normal_std) = (20.0, 8.0);
+                (i.lower_bound, i.upper_bound) = (Some(10.0), Some(35.0));
+            }),
+        );
+        assert_round_trip(
+            Distributions::Gamma,
+            inputs(|i| {
+                (i.gamma_shape, i.gamma_scale) = (2.0, 5.0);
+                i.lower_bound = Some(4.0);
+            }),
+        );
+    }
+
+    #[test]
+    fn quantiles_the_family_cannot_match_have_no_solution() {
+        // A normal is symmetric, so its median cannot sit this close to its P10.
+        assert!(matches!(
+            solve(
+                Distributions::Normal,
+                [10.0, 11.0, 30.0],
+                &Default::default()
+            ),
+            Err(QuantileError::NoSolution(Distributions::Normal))
+        ));
+    }
+}
@@ -0,0 +1,341 @@
+use crate::config::SimulationConfig;
+use crate::convergence::ConvergenceTarget;
+use crate::correlation::TRIAL_LEVEL_INPUTS;
+use crate::errors::SimulationError;
+use crate::mcs::{InputSeeds, run_batches, trial_averages, trial_rng};
+use crate::message::{CancellationToken, SimulationMessage};
+use ndarray_rand::rand::{Rng, RngCore, seq::SliceRandom};
+use ndarray_rand::rand_distr::Open01;
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc::Sender;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+/// Trials are numbered from 1, so the RNG streams of trial 0 are free for draws that a whole
+/// run shares, such as its Latin Hypercube permutations.
+const DESIGN_TRIAL: i64 = 0;
+
+/// Independent runs per strategy when comparing them. The spread of their estimates is the
+/// strategy's standard error, which holds for stratified and quasi-random samples alike.
+/// Twenty pin it down to within about a sixth.
+const COMPARISON_REPLICATIONS: usize = 20;
+
+/// Mixed into the master seed once per replication of a strategy comparison.
+const REPLICATION_SEED: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Bits of each Sobol coordinate.
+const SOBOL_BITS: usize = 32;
+
+/// How the once-per-trial inputs (workdays/month, retailers/day and conversion rate) are
+/// spread over the trials. Visit-level draws are always pseudo-random.
+#[derive(Debug, PartialEq, Clone, Copy, Default, EnumIter, Serialize, Deserialize)]
+pub enum SamplingStrategy {
+    #[default]
+    PseudoRandom,
+    /// Each input's range is cut into as many equally likely strata as there are trials, and
+    /// every stratum is drawn exactly once, in a random order per input.
+    LatinHypercube,
+    /// Points of the Sobol sequence with Owen scrambling. Best with a power-of-two number of
+    /// trials.
+    Sobol,
+}
+
+impl std::fmt::Display for SamplingStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SamplingStrategy::PseudoRandom => write!(f, "Pseudo-random"),
+            SamplingStrategy::LatinHypercube => write!(f, "Latin Hypercube"),
+            SamplingStrategy::Sobol => write!(f, "Scrambled Sobol"),
+        }
+    }
+}
+
+/// The uniforms every trial of a run feeds into its trial-level inputs' inverse CDFs, in
+/// [`crate::correlation::CorrelatedInput`] order. Built up front because Latin Hypercube
+/// strata are shared out across the whole run.
+///
+/// A run stopped early by a convergence target uses only the first trials' points. For a
+/// Sobol sequence those are as evenly spread as the sequence allows; for a Latin Hypercube
+/// they are a random subset of the strata.
+pub(crate) struct TrialDesign(Vec<[f64; TRIAL_LEVEL_INPUTS]>);
+
+impl TrialDesign {
+    /// The design of `trials` trials, or None if `strategy` is pseudo-random. Each input's
+    /// permutation or scrambling comes from its own seed in `seeds`, so reseeding one input
+    /// resamples it alone.
+    pub(crate) fn new(
+        strategy: SamplingStrategy,
+        seeds: &InputSeeds,
+        trials: usize,
+    ) -> Option<TrialDesign> {
+        let fill_input: fn(&mut [[f64; TRIAL_LEVEL_INPUTS]], usize, &mut ChaCha12Rng) =
+            match strategy {
+                SamplingStrategy::PseudoRandom => return None,
+                SamplingStrategy::LatinHypercube => latin_hypercube,
+                SamplingStrategy::Sobol => scrambled_sobol,
+            };
+        let mut points = vec![[0.0; TRIAL_LEVEL_INPUTS]; trials];
+        for (input, seed) in seeds.iter().enumerate().take(TRIAL_LEVEL_INPUTS) {
+            let mut rng = trial_rng(*seed, DESIGN_TRIAL, input as u8);
+            fill_input(&mut points, input, &mut rng);
+        }
+        Some(TrialDesign(points))
+    }
+
+    /// The point of trial `i`, counting from 1.
+    pub(crate) fn point(&self, i: i64) -> [f64; TRIAL_LEVEL_INPUTS] {
+        self.0[(i - 1) as usize]
+    }
+}
+
+/// Fills coordinate `input` of every point with one draw from each of `points.len()` equally
+/// likely strata, in a random order.
+fn latin_hypercube(points: &mut [[f64; TRIAL_LEVEL_INPUTS]], input: usize, rng: &mut ChaCha12Rng) {
+    let trials = points.len();
+    let mut strata: Vec<usize> = (0..trials).collect();
+    strata.shuffle(rng);
+    for (point, stratum) in points.iter_mut().zip(strata) {
+        let jitter: f64 = rng.sample(Open01);
+        point[input] = (stratum as f64 + jitter) / trials as f64;
+    }
+}
+
+/// Fills coordinate `input` of every point from Sobol dimension `input`, Owen-scrambled.
+fn scrambled_sobol(points: &mut [[f64; TRIAL_LEVEL_INPUTS]], input: usize, rng: &mut ChaCha12Rng) {
+    let directions = sobol_directions(input);
+    let scramble = rng.next_u32();
+    for (index, point) in points.iter_mut().enumerate() {
+        let x = owen_scramble(sobol(&directions, index as u32), scramble);
+        // The middle of the cell keeps every coordinate inside (0, 1).
+        point[input] = (x as f64 + 0.5) / 2f64.powi(SOBOL_BITS as i32);
+    }
+}
+
+/// Direction numbers of one of the first three Sobol dimensions, from Joe and Kuo's primitive
+/// polynomials and initial numbers.
+fn sobol_directions(dimension: usize) -> [u32; SOBOL_BITS] {
+    // (degree, coefficients, initial direction numbers) of each dimension's polynomial.
+    let (degree, coefficients, initial): (usize, u64, &[u64]) = match dimension {
+        0 => return std::array::from_fn(|k| 1 << (SOBOL_BITS - 1 - k)),
+        1 => (1, 0, &[1]),
+        _ => (2, 1, &[1, 3]),
+    };
+    let mut m = initial.to_vec();
+    for k in degree..SOBOL_BITS {
+        let mut next = m[k - degree] ^ (m[k - degree] << degree);
+        for j in 1..degree {
+            if (coefficients >> (degree - 1 - j)) & 1 == 1 {
+                next ^= m[k - j] << j;
+            }
+        }
+        m.push(next);
+    }
+    std::array::from_fn(|k| (m[k] << (SOBOL_BITS - 1 - k)) as u32)
+}
+
+/// Coordinate `index` of the Sobol sequence with these direction numbers.
+fn sobol(directions: &[u32; SOBOL_BITS], index: u32) -> u32 {
+    directions
+        .iter()
+        .enumerate()
+        .filter(|(bit, _)| (index >> bit) & 1 == 1)
+        .fold(0, |x, (_, direction)| x ^ direction)
+}
+
+/// Owen scrambling by hashing, after Burley (2020): a random permutation of each digit that
+/// depends on the digits above it, which keeps the sequence's stratification.
+fn owen_scramble(x: u32, seed: u32) -> u32 {
+    let mut x = x.reverse_bits();
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50_b47c);
+    x ^= x.wrapping_mul(0xb82f_1e52);
+    x ^= x.wrapping_mul(0xc7af_e638);
+    x ^= x.wrapping_mul(0x8d22_f6e6);
+    x.reverse_bits()
+}
+
+/// How precisely one strategy estimated the target statistic.
+#[derive(Debug, Clone)]
+pub struct StrategyResult {
+    pub strategy: SamplingStrategy,
+    pub estimate: f64,
+    /// Precision of one replication, in the terms of [`ConvergenceTarget::target`].
+    pub achieved: f64,
+    /// Trials one run would need to reach the target precision.
+    pub trials_needed: usize,
+}
+
+/// Result of running the same model under every sampling strategy.
+#[derive(Debug, Clone, Default)]
+pub struct StrategyComparison {
+    pub target: ConvergenceTarget,
+    /// Trials in each replication.
+    pub trials: usize,
+    pub replications: usize,
+    pub results: Vec<StrategyResult>,
+}
+
+/// Estimates how many trials each sampling strategy needs to reach the same precision of the
+/// per-trial commission, by the configured convergence target or else the default one.
+///
+/// Each strategy runs [`COMPARISON_REPLICATIONS`] independently seeded replications that
+/// share `config.trials` between them, so the comparison costs three ordinary runs. The
+/// spread of the replications' estimates gives each strategy's precision, which is then
+/// scaled to the target assuming error falls with the square root of the trial count. A Sobol
+/// sequence's error usually falls faster, so its figure is on the cautious side.
+pub fn compare_strategies(
+    config: &SimulationConfig,
+    progress_sender: Option<Sender<SimulationMessage>>,
+    cancellation: &CancellationToken,
+) -> Result<StrategyComparison, SimulationError> {
+    let comparison = compare(config, &progress_sender, cancellation);
+    if let Some(sender) = progress_sender {
+        let message = match &comparison {
+            Ok(comparison) => SimulationMessage::Sampling(comparison.clone()),
+            Err(SimulationError::Cancelled) => SimulationMessage::Cancelled,
+            Err(e) => SimulationMessage::Error(e.clone()),
+        };
+        let _ = sender.send(message);
+    }
+    comparison
+}
+
+fn compare(
+    config: &SimulationConfig,
+    progress_sender: &Option<Sender<SimulationMessage>>,
+    cancellation: &CancellationToken,
+) -> Result<StrategyComparison, SimulationError> {
+    config.validate()?;
+    let target = config.convergence.clone().unwrap_or_default();
+    let mut config = config.clone();
+    config.convergence = None;
+    config.trials = (config.trials / COMPARISON_REPLICATIONS).max(2);
+    let seed = config.seed;
+    let runs = SamplingStrategy::iter().count() * COMPARISON_REPLICATIONS;
+
+    let mut results = Vec::new();
+    for (s, strategy) in SamplingStrategy::iter().enumerate() {
+        config.sampling = strategy;
+        let mut estimates = Vec::with_capacity(COMPARISON_REPLICATIONS);
+        for replication in 0..COMPARISON_REPLICATIONS {
+            config.seed = seed ^ REPLICATION_SEED.wrapping_mul(replication as u64 + 1);
+            let run = s * COMPARISON_REPLICATIONS + replication;
+            let mut values = Vec::with_capacity(config.trials);
+            run_batches(&config, &None, cancellation, |df, batch| {
+                values.extend(trial_averages(df, "Commissions")?);
+                if let Some(sender) = progress_sender {
+                    let done = (batch.end - 1) as f32 / config.trials as f32;
+                    let progress = (run as f32 + done) / runs as f32;
+                    let _ = sender.send(SimulationMessage::Progress(progress));
+                }
+                Ok(false)
+            })?;
+            estimates.push(target.report(&values).estimate);
+        }
+        let n = estimates.len() as f64;
+        let estimate = estimates.iter().sum::<f64>() / n;
+        let variance = estimates
+            .iter()
+            .map(|x| (x - estimate).powi(2))
+            .sum::<f64>()
+            / (n - 1.0);
+        // Precision as the convergence target measures it, with the replications' spread
+        // standing in for the standard error.
+        let achieved = target.relative_precision(estimate, variance.sqrt());
+        let trials_needed = if target.target > 0.0 {
+            (config.trials as f64 * (achieved / target.target).powi(2)).ceil() as usize
+        } else {
+            usize::MAX
+        };
+        results.push(StrategyResult {
+            strategy,
+            estimate,
+            achieved,
+            trials_needed: trials_needed.max(1),
+        });
+    }
+    Ok(StrategyComparison {
+        target,
+        trials: config.trials,
+        replications: COMPARISON_REPLICATIONS,
+        results,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEEDS: InputSeeds = [7, 11, 13, 17, 19];
+
+    fn design(strategy: SamplingStrategy, trials: usize) -> Vec<[f64; TRIAL_LEVEL_INPUTS]> {
+        TrialDesign::new(strategy, &SEEDS, trials).unwrap().0
+    }
+
+    /// True if splitting `[0, 1)` into `strata` equal parts puts exactly one of `values` in each.
+    fn one_per_stratum(values: impl Iterator<Item = f64>, strata: usize) -> bool {
+        let mut hits = vec![0; strata];
+        for x in values {
+            hits[(x * strata as f64) as usize] += 1;
+        }
+        hits.iter().all(|&hits| hits == 1)
+    }
+
+    #[test]
+    fn pseudo_random_has_no_design() {
+        assert!(TrialDesign::new(SamplingStrategy::PseudoRandom, &SEEDS, 100).is_none());
+    }
+
+    #[test]
+    fn latin_hypercube_draws_every_stratum_once_per_input() {
+        let points = design(SamplingStrategy::LatinHypercube, 100);
+        for input in 0..TRIAL_LEVEL_INPUTS {
+            assert!(one_per_stratum(points.iter().map(|p| p[input]), 100));
+        }
+    }
+
+    #[test]
+    fn sobol_directions_follow_joe_and_kuo() {
+        // Each direction number is m_k / 2^k for odd integers m_k.
+        let m = |dimension: usize| -> Vec<u32> {
+            let directions = sobol_directions(dimension);
+            (0..6)
+                .map(|k| directions[k] >> (SOBOL_BITS - 1 - k))
+                .collect()
+        };
+        assert_eq!(m(0), [1, 1, 1, 1, 1, 1]);
+        assert_eq!(m(1), [1, 3, 5, 15, 17, 51]);
+        assert_eq!(m(2), [1, 3, 3, 9, 29, 23]);
+    }
+
+    #[test]
+    fn scrambled_sobol_points_are_stratified_at_powers_of_two() {
+        for trials in [16, 64, 256] {
+            let points = design(SamplingStrategy::Sobol, trials);
+            assert!(points.iter().flatten().all(|x| (0.0..1.0).contains(x)));
+            for input in 0..TRIAL_LEVEL_INPUTS {
+                assert!(
+                    one_per_stratum(points.iter().map(|p| p[input]), trials),
+                    "input {input} at {trials} trials"
+                );
+            }
+            // The first two dimensions form a (0, m, 2)-net: every box of area 1/trials holds
+            // exactly one point.
+            let mut columns = 1;
+            while columns <= trials {
+                let rows = trials / columns;
+                let boxes = points.iter().map(|p| {
+                    let cell =
+                        (p[0] * columns as f64) as usize * rows + (p[1] * rows as f64) as usize;
+                    (cell as f64 + 0.5) / trials as f64
+                });
+                assert!(
+                    one_per_stratum(boxes, trials),
+                    "{columns} x {rows} at {trials}"
+                );
+                columns *= 2;
+            }
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use crate::config::SimulationConfig;
+use crate::errors::ScenarioError;
+use serde::Serialize;
+use serde_json::Value;
+use std::{fs, path::Path};
+
+/// Format version written to new scenario files. Bump it whenever a change to
+/// [`SimulationConfig`] needs more than new fields with defaults, and teach [`upgrade`]
+/// how to bring the older files forward.
+pub const SCENARIO_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct ScenarioRef<'a> {
+    version: u32,
+    #[serde(flatten)]
+    config: &'a SimulationConfig,
+}
+
+/// Reads a scenario file. Files ending in `.json` are parsed as JSON, anything else as TOML.
+/// Files from older format versions are upgraded, files without a version are treated as
+/// version 1, and fields missing from the file keep their [`SimulationConfig::default`] values.
+pub fn load_scenario(path: &Path) -> Result<SimulationConfig, ScenarioError> {
+    let contents = fs::read_to_string(path)?;
+    let mut value: Value = if is_json(path) {
+        serde_json::from_str(&contents)?
+    } else {
+        toml::from_str(&contents)?
+    };
+    let version = match value.get("version") {
+        None => 1,
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .filter(|version| *version >= 1)
+            .ok_or(ScenarioError::InvalidVersion)?,
+    };
+    if version > SCENARIO_VERSION {
+        return Err(ScenarioError::NewerVersion(version));
+    }
+    upgrade(version, &mut value);
+    if let Some(table) = value.as_object_mut() {
+        table.remove("version");
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Writes `config` as a scenario file, as JSON if `path` ends in `.json` and TOML otherwise.
+pub fn save_scenario(path: &Path, config: &SimulationConfig) -> Result<(), ScenarioError> {
+    let scenario = ScenarioRef {
+        version: SCENARIO_VERSION,
+        config,
+    };
+    let contents = if is_json(path) {
+        serde_json::to_string_pretty(&scenario)?
+    } else {
+        toml::to_string(&scenario)?
+    };
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+/// Rewrites a scenario from format `version` into the current format.
+fn upgrade(_version: u32, _value: &mut Value) {
+    // Version 1 is the only format so far. Migrations from older versions go here,
+    // one version step at a time.
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+}
@@ -0,0 +1,305 @@
+use crate::config::SimulationConfig;
+use crate::correlation::{CorrelatedInput, NUM_CORRELATED_INPUTS};
+use crate::distributions::{DistributionInputs, Distributions};
+use crate::errors::SimulationError;
+use crate::mcs::{
+    InputSeeds, create_array_from_uniforms, run_batches_seeded, trial_averages,
+    truncated_conversion_rate,
+};
+use crate::message::{CancellationToken, SimulationMessage};
+use ndarray::arr1;
+use polars::prelude::*;
+use std::{
+    fs::{self, File},
+    path::Path,
+    sync::mpsc::Sender,
+};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+/// Where the GUI writes the sensitivity table, relative to the working directory.
+pub const DEFAULT_SENSITIVITY_PATH: &str = "mcs_data/sensitivity.csv";
+
+/// Output whose mean and P10 are compared. It includes the transport bonus so that every
+/// input, the bonus among them, can move it.
+pub const SENSITIVITY_METRIC: &str = "Comission with bonus";
+
+/// Percentiles a stochastic input is pinned to for its low and high runs.
+const LOW_PERCENTILE: f64 = 0.10;
+const HIGH_PERCENTILE: f64 = 0.90;
+
+/// Relative swing applied to the fixed rates, which have no distribution to take percentiles of.
+const CONSTANT_SWING: f64 = 0.10;
+
+/// An input swung by the one-at-a-time analysis.
+#[derive(Debug, PartialEq, Clone, Copy, EnumIter)]
+pub enum SensitivityInput {
+    Price,
+    RetailersPerDay,
+    WorkdaysPerMonth,
+    UnitsPerSale,
+    ConversionRate,
+    CommissionRate,
+    TransportBonus,
+}
+
+impl std::fmt::Display for SensitivityInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SensitivityInput::Price => write!(f, "Price"),
+            SensitivityInput::RetailersPerDay => write!(f, "Retailers/Day"),
+            SensitivityInput::WorkdaysPerMonth => write!(f, "Workdays/Month"),
+            SensitivityInput::UnitsPerSale => write!(f, "Units/Sale"),
+            SensitivityInput::ConversionRate => write!(f, "Conversion Rate"),
+            SensitivityInput::CommissionRate => write!(f, "Commission Rate"),
+            SensitivityInput::TransportBonus => write!(f, "Transport Bonus"),
+        }
+    }
+}
+
+/// Mean and P10 of [`SENSITIVITY_METRIC`] over each trial's average month.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Outcome {
+    pub mean: f64,
+    pub p10: f64,
+}
+
+/// The outcomes of one input's low and high runs.
+#[derive(Debug, Clone)]
+pub struct Swing {
+    pub input: SensitivityInput,
+    pub low_value: f64,
+    pub high_value: f64,
+    pub low: Outcome,
+    pub high: Outcome,
+}
+
+impl Swing {
+    /// Spread of the mean between the low and high runs, which orders the tornado.
+    pub fn mean_range(&self) -> f64 {
+        (self.high.mean - self.low.mean).abs()
+    }
+}
+
+/// Result of a one-at-a-time sensitivity analysis, with swings ordered from the largest
+/// effect on the mean to the smallest.
+#[derive(Debug, Clone, Default)]
+pub struct SensitivityAnalysis {
+    pub table_path: String,
+    pub base: Outcome,
+    pub swings: Vec<Swing>,
+}
+
+/// Swings each input between its low and high values while every other input keeps its base
+/// distribution, and writes the results to a CSV table at `table_path`.
+///
+/// Stochastic inputs are pinned to their 10th and 90th percentiles, and the commission rate
+/// and transport bonus are moved 10% either side of their base values. Every run reuses the
+/// configured seed, so differences between runs come from the swing rather than sampling noise.
+pub fn run_sensitivity(
+    config: &SimulationConfig,
+    table_path: &Path,
+    progress_sender: Option<Sender<SimulationMessage>>,
+    cancellation: &CancellationToken,
+) -> Result<SensitivityAnalysis, SimulationError> {
+    let analysis = analyse(config, table_path, &progress_sender, cancellation);
+    if let Some(sender) = progress_sender {
+        let message = match &analysis {
+            Ok(analysis) => SimulationMessage::Sensitivity(analysis.clone()),
+            Err(SimulationError::Cancelled) => SimulationMessage::Cancelled,
+            Err(e) => SimulationMessage::Error(e.clone()),
+        };
+        let _ = sender.send(message);
+    }
+    analysis
+}
+
+fn analyse(
+    config: &SimulationConfig,
+    table_path: &Path,
+    progress_sender: &Option<Sender<SimulationMessage>>,
+    cancellation: &CancellationToken,
+) -> Result<SensitivityAnalysis, SimulationError> {
+    config.validate()?;
+    let mut config = config.clone();
+    config.convergence = None;
+    let runs = 1 + 2 * SensitivityInput::iter().count();
+    let mut run = 0;
+    let mut outcome = |config: &SimulationConfig| {
+        let result = simulate(config, run, runs, progress_sender, cancellation);
+        run += 1;
+        result
+    };
+
+    let base = outcome(&config)?;
+    let mut swings = Vec::new();
+    for input in SensitivityInput::iter() {
+        let (low_value, high_value) = swing_values(&config, input)?;
+        let low = outcome(&with_input(&config, input, low_value))?;
+        let high = outcome(&with_input(&config, input, high_value))?;
+        swings.push(Swing {
+            input,
+            low_value,
+            high_value,
+            low,
+            high,
+        });
+    }
+    swings.sort_by(|a, b| b.mean_range().total_cmp(&a.mean_range()));
+
+    let mut df = table(base, &swings)?;
+    let table_path = write_table(table_path, &mut df)?;
+
+    Ok(SensitivityAnalysis {
+        table_path,
+        base,
+        swings,
+    })
+}
+
+/// Runs `config` without writing its output and reports progress as run `run` of `runs`.
+fn simulate(
+    config: &SimulationConfig,
+    run: usize,
+    runs: usize,
+    progress_sender: &Option<Sender<SimulationMessage>>,
+    cancellation: &CancellationToken,
+) -> Result<Outcome, SimulationError> {
+    let seeds = [config.seed; NUM_CORRELATED_INPUTS];
+    let mut values = trial_values(config, &seeds, run, runs, progress_sender, cancellation)?;
+    values.sort_by(f64::total_cmp);
+    Ok(Outcome {
+        mean: values.iter().sum::<f64>() / values.len() as f64,
+        p10: quantile(&values, 0.10),
+    })
+}
+
+/// Each trial's average month of [`SENSITIVITY_METRIC`], in trial order, from a run of
+/// `config` that is not written out. Progress is reported as run `run` of `runs`.
+pub(crate) fn trial_values(
+    config: &SimulationConfig,
+    seeds: &InputSeeds,
+    run: usize,
+    runs: usize,
+    progress_sender: &Option<Sender<SimulationMessage>>,
+    cancellation: &CancellationToken,
+) -> Result<Vec<f64>, SimulationError> {
+    let mut values = Vec::with_capacity(config.trials);
+    run_batches_seeded(config, seeds, &None, cancellation, |df, batch| {
+        values.extend(trial_averages(df, SENSITIVITY_METRIC)?);
+        if let Some(sender) = progress_sender {
+            let done = (batch.end - 1) as f32 / config.trials as f32;
+            let _ = sender.send(SimulationMessage::Progress(
+                (run as f32 + done) / runs as f32,
+            ));
+        }
+        Ok(false)
+    })?;
+    Ok(values)
+}
+
+/// Low and high values of `input`.
+fn swing_values(
+    config: &SimulationConfig,
+    input: SensitivityInput,
+) -> Result<(f64, f64), SimulationError> {
+    let input = match input {
+        SensitivityInput::Price => CorrelatedInput::Price,
+        SensitivityInput::RetailersPerDay => CorrelatedInput::RetailersPerDay,
+        SensitivityInput::WorkdaysPerMonth => CorrelatedInput::WorkdaysPerMonth,
+        SensitivityInput::UnitsPerSale => CorrelatedInput::Units,
+        SensitivityInput::ConversionRate => CorrelatedInput::ConversionRate,
+        SensitivityInput::CommissionRate => {
+            return Ok(relative_swing(config.commission_rate));
+        }
+        SensitivityInput::TransportBonus => {
+            return Ok(relative_swing(config.transport_bonus));
+        }
+    };
+    // The run truncates the conversion rate to 0-100%, so its percentiles must come from the
+    // truncated distribution too, or a pinned value could fall outside it.
+    let params = match input {
+        CorrelatedInput::ConversionRate => truncated_conversion_rate(config),
+        _ => config.input(input).clone(),
+    };
+    let values = create_array_from_uniforms(&params, &arr1(&[LOW_PERCENTILE, HIGH_PERCENTILE]))
+        .map_err(|source| SimulationError::Sampling {
+            input,
+            trial: None,
+            source,
+        })?;
+    Ok((values[0], values[1]))
+}
+
+fn relative_swing(value: f64) -> (f64, f64) {
+    (
+        value * (1.0 - CONSTANT_SWING),
+        value * (1.0 + CONSTANT_SWING),
+    )
+}
+
+/// A copy of `config` with `input` held at `value`.
+fn with_input(config: &SimulationConfig, input: SensitivityInput, value: f64) -> SimulationConfig {
+    let mut config = config.clone();
+    let constant = (
+        Distributions::Constant,
+        DistributionInputs {
+            constant_val: value,
+            ..Default::default()
+        },
+    );
+    match input {
+        SensitivityInput::Price => config.prices = constant,
+        SensitivityInput::RetailersPerDay => config.retailers_per_day = constant,
+        SensitivityInput::WorkdaysPerMonth => config.workdays_per_month = constant,
+        SensitivityInput::UnitsPerSale => config.units = constant,
+        SensitivityInput::ConversionRate => config.conversion_rate = constant,
+        SensitivityInput::CommissionRate => config.commission_rate = value,
+        SensitivityInput::TransportBonus => config.transport_bonus = value,
+    }
+    config
+}
+
+/// Linearly interpolated quantile of sorted `values`, matching the summary statistics.
+fn quantile(values: &[f64], p: f64) -> f64 {
+    if values.is_empty() {
+        return f64::NAN;
+    }
+    let rank = p * (values.len() - 1) as f64;
+    let (below, above) = (rank.floor() as usize, rank.ceil() as usize);
+    values[below] + (rank - below as f64) * (values[above] - values[below])
+}
+
+fn table(base: Outcome, swings: &[Swing]) -> Result<DataFrame, PolarsError> {
+    let column = |value: fn(&Swing) -> f64| swings.iter().map(value).collect::<Vec<f64>>();
+    df!(
+        "input" => swings.iter().map(|swing| swing.input.to_string()).collect::<Vec<_>>(),
+        "low_value" => column(|swing| swing.low_value),
+        "high_value" => column(|swing| swing.high_value),
+        "low_mean" => column(|swing| swing.low.mean),
+        "high_mean" => column(|swing| swing.high.mean),
+        "low_p10" => column(|swing| swing.low.p10),
+        "high_p10" => column(|swing| swing.high.p10),
+        "base_mean" => vec![base.mean; swings.len()],
+        "base_p10" => vec![base.p10; swings.len()],
+    )
+}
+
+/// Writes `df` as a CSV table at `table_path` and returns the table's absolute path.
+pub(crate) fn write_table(
+    table_path: &Path,
+    df: &mut DataFrame,
+) -> Result<String, SimulationError> {
+    let io_error = |e| SimulationError::io(table_path, e);
+    if let Some(parent_dir) = table_path.parent() {
+        fs::create_dir_all(parent_dir).map_err(io_error)?;
+    }
+    let mut file = File::create(table_path).map_err(io_error)?;
+    CsvWriter::new(&mut file)
+        .finish(df)
+        .map_err(|e| SimulationError::io(table_path, e))?;
+    Ok(fs::canonicalize(table_path)
+        .map_err(io_error)?
+        .to_string_lossy()
+        .to_string())
+}
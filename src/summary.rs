@@ -0,0 +1,211 @@
+use crate::convergence::ConvergenceReport;
+use crate::errors::SimulationError;
+use crate::variance_reduction::VarianceReduction;
+use polars::prelude::*;
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
+
+/// Output columns that get summary statistics.
+pub const SUMMARY_METRICS: [&str; 3] = ["Commissions", "Sale Value", "Comission with bonus"];
+
+/// Tail probability used for the value at risk and conditional value at risk.
+const RISK_LEVEL: f64 = 0.05;
+
+/// Distribution of one metric across trials.
+#[derive(Debug, Clone, Default)]
+pub struct Statistics {
+    pub mean: f64,
+    pub std: f64,
+    pub p5: f64,
+    pub p10: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p95: f64,
+    /// How far the metric falls below its mean at the 5th percentile, i.e. `mean - P5`.
+    pub value_at_risk: f64,
+    /// How far the metric falls below its mean on average in the worst 5% of outcomes,
+    /// i.e. `mean - E[x | x <= P5]`.
+    pub conditional_value_at_risk: f64,
+    /// Share of outcomes below the income floor.
+    pub prob_below_floor: f64,
+}
+
+/// Statistics of one output column.
+#[derive(Debug, Clone, Default)]
+pub struct MetricSummary {
+    pub metric: String,
+    /// Across trials, of each trial's average month.
+    pub per_trial: Statistics,
+    /// Across trials, for each month, keyed by month number.
+    pub per_month: Vec<(i64, Statistics)>,
+    /// Each trial's average month, sorted ascending.
+    pub trial_values: Vec<f64>,
+}
+
+/// What a finished run hands back: where its files are and its headline statistics.
+#[derive(Debug, Clone, Default)]
+pub struct SimulationSummary {
+    pub output_path: String,
+    pub summary_path: String,
+    pub income_floor: f64,
+    pub metrics: Vec<MetricSummary>,
+    /// How precise the run got, if it was stopped by a convergence target.
+    pub convergence: Option<ConvergenceReport>,
+    /// The variance-reduced mean commission, if the run used antithetic pairs or a control
+    /// variate.
+    pub variance_reduction: Option<VarianceReduction>,
+}
+
+/// Computes per-trial and per-month statistics of the [`SUMMARY_METRICS`] in the parquet at
+/// `output_path` and writes them to a `_summary.csv` file next to it.
+///
+/// Per-trial statistics are taken over each trial's average month, so both scopes are in
+/// monthly terms and compare against the same `income_floor`.
+pub fn summarize(
+    output_path: &str,
+    income_floor: f64,
+) -> Result<SimulationSummary, SimulationError> {
+    let lf = LazyFrame::scan_parquet(PlPath::new(output_path), ScanArgsParquet::default())?;
+    // One row per trial in trial order, in one chunk, so the statistics below sum the trials in
+    // the same order whatever the thread count.
+    let mut trials = lf
+        .clone()
+        .group_by_stable([col("distributor_id")])
+        .agg(SUMMARY_METRICS.map(|metric| col(metric).mean()))
+        .collect()?;
+    trials.as_single_chunk_par();
+
+    let mut frames = Vec::new();
+    for metric in SUMMARY_METRICS {
+        let per_trial = trials
+            .clone()
+            .lazy()
+            .select(statistics_exprs(metric, income_floor))
+            .with_columns([
+                lit("Per trial").alias("scope"),
+                lit(NULL).cast(DataType::Int64).alias("month"),
+            ]);
+        let per_month = lf
+            .clone()
+            .group_by([col("month")])
+            .agg(statistics_exprs(metric, income_floor))
+            .sort(["month"], SortMultipleOptions::default())
+            .with_column(lit("Per month").alias("scope"));
+        for frame in [per_trial, per_month] {
+            frames.push(frame.select([
+                col("scope"),
+                col("month"),
+                lit(metric).alias("metric"),
+                all().exclude_cols(["scope", "month"]).as_expr(),
+            ]));
+        }
+    }
+    let mut df = concat(frames, UnionArgs::default())?.collect()?;
+
+    let summary_path = summary_path(output_path);
+    let io_error = |e| SimulationError::io(&summary_path, e);
+    let mut file = File::create(&summary_path).map_err(io_error)?;
+    CsvWriter::new(&mut file)
+        .finish(&mut df)
+        .map_err(|e| SimulationError::io(&summary_path, e))?;
+    let summary_path = fs::canonicalize(&summary_path)
+        .map_err(io_error)?
+        .to_string_lossy()
+        .to_string();
+
+    let mut metrics = metric_summaries(&df)?;
+    for summary in &mut metrics {
+        let mut values: Vec<f64> = trials
+            .column(&summary.metric)?
+            .f64()?
+            .into_no_null_iter()
+            .collect();
+        values.sort_by(f64::total_cmp);
+        summary.trial_values = values;
+    }
+
+    Ok(SimulationSummary {
+        output_path: output_path.to_string(),
+        summary_path,
+        income_floor,
+        metrics,
+        convergence: None,
+        variance_reduction: None,
+    })
+}
+
+/// `output.parquet` becomes `output_summary.csv` in the same directory.
+fn summary_path(output_path: &str) -> PathBuf {
+    let path = Path::new(output_path);
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.with_file_name(format!("{}_summary.csv", stem))
+}
+
+fn statistics_exprs(metric: &str, income_floor: f64) -> Vec<Expr> {
+    let x = col(metric);
+    let quantile = |q: f64| x.clone().quantile(lit(q), QuantileMethod::Linear);
+    let tail = x.clone().filter(x.clone().lt_eq(quantile(RISK_LEVEL)));
+    vec![
+        x.clone().mean().alias("mean"),
+        x.clone().std(1).alias("std"),
+        quantile(0.05).alias("p5"),
+        quantile(0.10).alias("p10"),
+        quantile(0.50).alias("p50"),
+        quantile(0.90).alias("p90"),
+        quantile(0.95).alias("p95"),
+        (x.clone().mean() - quantile(RISK_LEVEL)).alias("value_at_risk_95"),
+        (x.clone().mean() - tail.mean()).alias("cvar_95"),
+        x.lt(lit(income_floor))
+            .cast(DataType::Float64)
+            .mean()
+            .alias("prob_below_floor"),
+    ]
+}
+
+/// Reads the summary frame back into one [`MetricSummary`] per metric.
+fn metric_summaries(df: &DataFrame) -> Result<Vec<MetricSummary>, PolarsError> {
+    let value = |name: &str, row: usize| -> Result<f64, PolarsError> {
+        Ok(df.column(name)?.f64()?.get(row).unwrap_or(f64::NAN))
+    };
+    let scopes = df.column("scope")?.str()?;
+    let metrics = df.column("metric")?.str()?;
+    let months = df.column("month")?.i64()?;
+
+    let mut summaries: Vec<MetricSummary> = SUMMARY_METRICS
+        .iter()
+        .map(|metric| MetricSummary {
+            metric: metric.to_string(),
+            ..Default::default()
+        })
+        .collect();
+    for row in 0..df.height() {
+        let statistics = Statistics {
+            mean: value("mean", row)?,
+            std: value("std", row)?,
+            p5: value("p5", row)?,
+            p10: value("p10", row)?,
+            p50: value("p50", row)?,
+            p90: value("p90", row)?,
+            p95: value("p95", row)?,
+            value_at_risk: value("value_at_risk_95", row)?,
+            conditional_value_at_risk: value("cvar_95", row)?,
+            prob_below_floor: value("prob_below_floor", row)?,
+        };
+        let Some(summary) = summaries
+            .iter_mut()
+            .find(|summary| Some(summary.metric.as_str()) == metrics.get(row))
+        else {
+            continue;
+        };
+        match (scopes.get(row), months.get(row)) {
+            (Some("Per month"), Some(month)) => summary.per_month.push((month, statistics)),
+            _ => summary.per_trial = statistics,
+        }
+    }
+    Ok(summaries)
+}
@@ -0,0 +1,332 @@
+use crate::config::SimulationConfig;
+use crate::correlation::CorrelatedInput;
+use crate::distributions::{DistributionInputs, Distributions};
+use crate::empirical;
+use crate::errors::{
+    DistributionError, EmpiricalError, InvalidField, MetalogError, Requirement, ValidationError,
+};
+use crate::mcs::create_array_from_uniforms;
+use crate::metalog::Metalog;
+use ndarray::arr1;
+use std::path::Path;
+use strum::IntoEnumIterator;
+
+/// A field of the model's inputs. Shown by its name in a scenario file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Field {
+    /// A distribution parameter on its own, e.g. `normal_std`, or `distribution` for the
+    /// distribution as a whole.
+    Parameter(&'static str),
+    /// A distribution parameter of one of the model's stochastic inputs.
+    Input(CorrelatedInput, &'static str),
+    CommissionRate,
+    TransportBonus,
+    IncomeFloor,
+    NumMonths,
+    Trials,
+    ConvergenceTarget,
+    Correlation,
+    ControlVariate,
+}
+
+impl std::fmt::Display for Field {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Field::Parameter(parameter) => write!(f, "{}", parameter),
+            Field::Input(input, parameter) => {
+                let table = match input {
+                    CorrelatedInput::WorkdaysPerMonth => "workdays_per_month",
+                    CorrelatedInput::RetailersPerDay => "retailers_per_day",
+                    CorrelatedInput::ConversionRate => "conversion_rate",
+                    CorrelatedInput::Price => "prices",
+                    CorrelatedInput::Units => "units",
+                };
+                write!(f, "{}.{}", table, parameter)
+            }
+            Field::CommissionRate => write!(f, "commission_rate"),
+            Field::TransportBonus => write!(f, "transport_bonus"),
+            Field::IncomeFloor => write!(f, "income_floor"),
+            Field::NumMonths => write!(f, "num_months"),
+            Field::Trials => write!(f, "trials"),
+            Field::ConvergenceTarget => write!(f, "convergence.target"),
+            Field::Correlation => write!(f, "correlation"),
+            Field::ControlVariate => write!(f, "control_variate"),
+        }
+    }
+}
+
+/// Gathers every broken requirement instead of stopping at the first.
+#[derive(Default)]
+struct Problems(Vec<InvalidField>);
+
+impl Problems {
+    fn check(&mut self, holds: bool, field: Field, requirement: Requirement) {
+        if !holds {
+            self.0.push(InvalidField { field, requirement });
+        }
+    }
+
+    fn finite(&mut self, field: Field, x: f64) {
+        self.check(x.is_finite(), field, Requirement::Finite);
+    }
+
+    fn positive(&mut self, field: Field, x: f64) {
+        self.check(x.is_finite() && x > 0.0, field, Requirement::Positive);
+    }
+
+    fn probability(&mut self, field: Field, p: f64) {
+        self.check(
+            (0.0..=1.0).contains(&p),
+            field,
+            Requirement::Within(0.0, 1.0),
+        );
+    }
+
+    /// `min <= mode <= max` with `min < max`, each finite.
+    fn ordered(&mut self, [min, mode, max]: [(&'static str, f64); 3]) {
+        for (name, x) in [min, mode, max] {
+            self.finite(Field::Parameter(name), x);
+        }
+        self.check(
+            mode.1 >= min.1,
+            Field::Parameter(mode.0),
+            Requirement::AtLeast(min.0),
+        );
+        self.check(
+            mode.1 <= max.1,
+            Field::Parameter(mode.0),
+            Requirement::AtMost(max.0),
+        );
+        self.check(
+            max.1 > min.1,
+            Field::Parameter(max.0),
+            Requirement::Above(min.0),
+        );
+    }
+
+    fn into_result(self) -> Result<(), ValidationError> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError(self.0))
+        }
+    }
+}
+
+impl DistributionInputs {
+    /// Checks the parameters `distribution` uses, and the truncation bounds, listing every one
+    /// that is out of range.
+    pub fn validate(&self, distribution: Distributions) -> Result<(), ValidationError> {
+        use Field::Parameter as P;
+        let mut problems = Problems::default();
+        match distribution {
+            Distributions::Uniform => {
+                problems.finite(P("uniform_min"), self.uniform_min);
+                problems.finite(P("uniform_max"), self.uniform_max);
+                problems.check(
+                    self.uniform_max > self.uniform_min,
+                    P("uniform_max"),
+                    Requirement::Above("uniform_min"),
+                );
+            }
+            Distributions::Normal => {
+                problems.finite(P("normal_mean"), self.normal_mean);
+                problems.positive(P("normal_std"), self.normal_std);
+            }
+            Distributions::Bernoulli => {
+                problems.probability(P("bernoulli_prob"), self.bernoulli_prob)
+            }
+            Distributions::Constant => problems.finite(P("constant_val"), self.constant_val),
+            Distributions::Triangular => problems.ordered([
+                ("triangular_min", self.triangular_min),
+                ("triangular_mode", self.triangular_mode),
+                ("triangular_max", self.triangular_max),
+            ]),
+            Distributions::Pert => problems.ordered([
+                ("pert_min", self.pert_min),
+                ("pert_mode", self.pert_mode),
+                ("pert_max", self.pert_max),
+            ]),
+            Distributions::LogNormal => {
+                problems.finite(P("lognormal_mu"), self.lognormal_mu);
+                problems.positive(P("lognormal_sigma"), self.lognormal_sigma);
+            }
+            Distributions::Gamma => {
+                problems.positive(P("gamma_shape"), self.gamma_shape);
+                problems.positive(P("gamma_scale"), self.gamma_scale);
+            }
+            Distributions::Beta => {
+                problems.positive(P("beta_alpha"), self.beta_alpha);
+                problems.positive(P("beta_beta"), self.beta_beta);
+                problems.finite(P("beta_min"), self.beta_min);
+                problems.finite(P("beta_max"), self.beta_max);
+                problems.check(
+                    self.beta_max > self.beta_min,
+                    P("beta_max"),
+                    Requirement::Above("beta_min"),
+                );
+            }
+            Distributions::Weibull => {
+                problems.positive(P("weibull_shape"), self.weibull_shape);
+                problems.positive(P("weibull_scale"), self.weibull_scale);
+            }
+            Distributions::Poisson => problems.positive(P("poisson_lambda"), self.poisson_lambda),
+            Distributions::NegativeBinomial => {
+                problems.positive(P("negbinomial_r"), self.negbinomial_r);
+                problems.check(
+                    self.negbinomial_p > 0.0 && self.negbinomial_p < 1.0,
+                    P("negbinomial_p"),
+                    Requirement::StrictlyWithin(0.0, 1.0),
+                );
+            }
+            Distributions::Binomial => problems.probability(P("binomial_p"), self.binomial_p),
+            Distributions::DiscreteUniform => problems.check(
+                self.discrete_max >= self.discrete_min,
+                P("discrete_max"),
+                Requirement::AtLeast("discrete_min"),
+            ),
+            Distributions::DiscreteTable => {
+                let table = &self.discrete_table;
+                problems.check(
+                    !table.is_empty(),
+                    P("discrete_table"),
+                    Requirement::NonEmpty,
+                );
+                problems.check(
+                    table.iter().all(|row| row.value.is_finite()),
+                    P("discrete_table"),
+                    Requirement::Finite,
+                );
+                problems.check(
+                    table.iter().all(|row| row.weight >= 0.0),
+                    P("discrete_table"),
+                    Requirement::NonNegativeWeights,
+                );
+                let total: f64 = table.iter().map(|row| row.weight).sum();
+                problems.check(
+                    table.is_empty() || (total.is_finite() && total > 0.0),
+                    P("discrete_table"),
+                    Requirement::PositiveTotalWeight,
+                );
+            }
+            Distributions::Empirical => {
+                if self.empirical_path.is_empty() {
+                    problems.check(false, P("empirical_path"), Requirement::NonEmpty);
+                } else if let Err(e) =
+                    empirical::load(Path::new(&self.empirical_path), &self.empirical_column)
+                {
+                    let field = match e {
+                        EmpiricalError::Read(_) => "empirical_path",
+                        EmpiricalError::MissingColumn(_) | EmpiricalError::NoData => {
+                            "empirical_column"
+                        }
+                    };
+                    problems.check(false, P(field), Requirement::Other(e.to_string()));
+                }
+            }
+            Distributions::Metalog => {
+                if let Err(e) =
+                    Metalog::new(&self.metalog_points, self.metalog_lower, self.metalog_upper)
+                {
+                    let field = match e {
+                        MetalogError::Limits => "metalog_upper",
+                        _ => "metalog_points",
+                    };
+                    problems.check(false, P(field), Requirement::Other(e.to_string()));
+                }
+            }
+        }
+        for (name, bound) in [
+            ("lower_bound", self.lower_bound),
+            ("upper_bound", self.upper_bound),
+        ] {
+            if let Some(bound) = bound {
+                problems.check(!bound.is_nan(), P(name), Requirement::Finite);
+            }
+        }
+        if let (Some(lower), Some(upper)) = (self.lower_bound, self.upper_bound) {
+            problems.check(
+                upper >= lower,
+                P("upper_bound"),
+                Requirement::AtLeast("lower_bound"),
+            );
+        }
+
+        // Anything the rules above let through still has to give a quantile.
+        if problems.0.is_empty()
+            && let Err(e) = create_array_from_uniforms(&(distribution, self.clone()), &arr1(&[0.5]))
+        {
+            let requirement = match e {
+                DistributionError::Truncation => Requirement::ProbabilityWithinBounds,
+                e => Requirement::Other(e.to_string()),
+            };
+            problems.check(false, P("distribution"), requirement);
+        }
+        problems.into_result()
+    }
+}
+
+impl SimulationConfig {
+    /// Checks every input, listing each field that is out of range.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let mut problems = Problems::default();
+        for input in CorrelatedInput::iter() {
+            let (distribution, inputs) = self.input(input);
+            let Err(ValidationError(fields)) = inputs.validate(*distribution) else {
+                continue;
+            };
+            problems
+                .0
+                .extend(fields.into_iter().map(|invalid| InvalidField {
+                    field: match invalid.field {
+                        Field::Parameter(parameter) => Field::Input(input, parameter),
+                        field => field,
+                    },
+                    requirement: invalid.requirement,
+                }));
+        }
+        // The engine truncates the conversion rate to 0-100%, so one mostly outside that range
+        // was almost certainly entered in the wrong units.
+        if !problems.0.iter().any(|invalid| {
+            matches!(
+                invalid.field,
+                Field::Input(CorrelatedInput::ConversionRate, _)
+            )
+        }) && let Ok(median) = create_array_from_uniforms(&self.conversion_rate, &arr1(&[0.5]))
+        {
+            problems.check(
+                (0.0..=100.0).contains(&median[0]),
+                Field::Input(CorrelatedInput::ConversionRate, "distribution"),
+                Requirement::MedianWithin(0.0, 100.0),
+            );
+        }
+
+        problems.check(
+            (0.0..=100.0).contains(&self.commission_rate),
+            Field::CommissionRate,
+            Requirement::Within(0.0, 100.0),
+        );
+        problems.check(
+            self.transport_bonus.is_finite() && self.transport_bonus >= 0.0,
+            Field::TransportBonus,
+            Requirement::NonNegative,
+        );
+        problems.finite(Field::IncomeFloor, self.income_floor);
+        problems.check(self.num_months > 0, Field::NumMonths, Requirement::Positive);
+        problems.check(self.trials > 0, Field::Trials, Requirement::Positive);
+        if let Some(convergence) = &self.convergence {
+            problems.positive(Field::ConvergenceTarget, convergence.target);
+        }
+        if !self.correlation.is_identity() {
+            if let Err(e) = self.correlation.cholesky() {
+                problems.check(false, Field::Correlation, Requirement::Other(e.to_string()));
+            }
+            problems.check(
+                !self.control_variate,
+                Field::ControlVariate,
+                Requirement::IndependentInputs,
+            );
+        }
+        problems.into_result()
+    }
+}
@@ -0,0 +1,222 @@
+use crate::config::SimulationConfig;
+use crate::correlation::CorrelatedInput;
+use crate::distributions::{DistributionInputs, Distributions};
+use crate::errors::SimulationError;
+use crate::mcs::{create_array_from_uniforms, to_count, truncated_conversion_rate};
+use ndarray::Array1;
+
+/// Evenly spaced quantiles an input's expected value is averaged over.
+const EXPECTATION_GRID_POINTS: usize = 100_000;
+
+/// The mean of the per-trial average monthly commission as estimated with the run's variance
+/// reduction, next to what the plain average of as many independent trials would give.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VarianceReduction {
+    pub antithetic: bool,
+    pub control_variate: bool,
+    pub estimate: f64,
+    pub standard_error: f64,
+    /// Standard error of the plain average.
+    pub plain_standard_error: f64,
+    /// Variance of the plain average over that of the reduced estimate: how many times more
+    /// trials plain Monte Carlo would need for the same precision.
+    pub factor: f64,
+}
+
+impl std::fmt::Display for VarianceReduction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let techniques = match (self.antithetic, self.control_variate) {
+            (true, true) => "antithetic pairs and a control variate",
+            (true, false) => "antithetic pairs",
+            (false, _) => "a control variate",
+        };
+        write!(
+            f,
+            "Mean commission with {} {:.2} ± {:.2} (plain ± {:.2}), variance reduced {:.1}x",
+            techniques, self.estimate, self.standard_error, self.plain_standard_error, self.factor
+        )
+    }
+}
+
+/// A trial's expected sale value given how many of its visits converted, which the control
+/// variate estimator corrects the commission by. Its mean is known from the inputs alone:
+/// `E[price] · E[units] · E[conversion rate] · E[visits]`, with `E[visits]` the product of the
+/// expected workdays/month and retailers/day.
+pub(crate) struct ControlVariate {
+    /// `E[price] · E[units]`, the expected sale value of one converted visit.
+    per_conversion: f64,
+    /// Expected sale value per month.
+    mean: f64,
+}
+
+impl ControlVariate {
+    /// The control of `config`. Its mean is a product of the inputs' means only if they are
+    /// independent, which [`SimulationConfig::validate`] requires of a run with a control.
+    pub(crate) fn new(config: &SimulationConfig) -> Result<ControlVariate, SimulationError> {
+        let mean = |input, count| expectation(config.input(input), input, count);
+        let per_conversion =
+            mean(CorrelatedInput::Price, false)? * mean(CorrelatedInput::Units, true)?;
+        let visits = mean(CorrelatedInput::WorkdaysPerMonth, true)?
+            * mean(CorrelatedInput::RetailersPerDay, true)?;
+        let conversion_rate = expectation(
+            &truncated_conversion_rate(config),
+            CorrelatedInput::ConversionRate,
+            false,
+        )? / 100.0;
+        Ok(ControlVariate {
+            per_conversion,
+            mean: per_conversion * conversion_rate * visits,
+        })
+    }
+
+    /// Each trial's control, from its average monthly conversions.
+    pub(crate) fn values(&self, conversions: &[f64]) -> Vec<f64> {
+        conversions
+            .iter()
+            .map(|conversions| conversions * self.per_conversion)
+            .collect()
+    }
+}
+
+/// Expected value of an input as the engine draws it, counts rounded and bounds applied, by
+/// averaging its quantile function over an even grid.
+fn expectation(
+    params: &(Distributions, DistributionInputs),
+    input: CorrelatedInput,
+    count: bool,
+) -> Result<f64, SimulationError> {
+    let uniforms = Array1::from_iter(
+        (0..EXPECTATION_GRID_POINTS).map(|i| (i as f64 + 0.5) / EXPECTATION_GRID_POINTS as f64),
+    );
+    let values = create_array_from_uniforms(params, &uniforms).map_err(|source| {
+        SimulationError::Sampling {
+            input,
+            trial: None,
+            source,
+        }
+    })?;
+    let values = if count {
+        values.mapv(|x| to_count(params.0, x))
+    } else {
+        values
+    };
+    Ok(values.mean().unwrap_or(f64::NAN))
+}
+
+/// Estimates the mean commission from each trial's average monthly `commissions`, in trial
+/// order, and with a control variate each trial's control.
+///
+/// With antithetic pairs, each pair's average is one independent observation and an unpaired
+/// last trial is left out. With a control variate, its optimal coefficient is estimated from
+/// the same observations. None with fewer than two observations.
+pub(crate) fn estimate(
+    commissions: &[f64],
+    control: Option<(&ControlVariate, &[f64])>,
+    antithetic: bool,
+) -> Option<VarianceReduction> {
+    let (_, plain_variance) = mean_and_variance(commissions)?;
+    let plain_standard_error = (plain_variance / commissions.len() as f64).sqrt();
+
+    let observations = |values: &[f64]| -> Vec<f64> {
+        if antithetic {
+            values
+                .chunks_exact(2)
+                .map(|pair| 0.5 * (pair[0] + pair[1]))
+                .collect()
+        } else {
+            values.to_vec()
+        }
+    };
+    let y = observations(commissions);
+    let adjusted = match control {
+        Some((control, values)) => {
+            let x = observations(values);
+            let (y_mean, _) = mean_and_variance(&y)?;
+            let (x_mean, x_variance) = mean_and_variance(&x)?;
+            let covariance = y
+                .iter()
+                .zip(&x)
+                .map(|(y, x)| (y - y_mean) * (x - x_mean))
+                .sum::<f64>()
+                / (y.len() - 1) as f64;
+            // A control that never varies carries no information.
+            let beta = if x_variance > 0.0 {
+                covariance / x_variance
+            } else {
+                0.0
+            };
+            y.iter()
+                .zip(&x)
+                .map(|(y, x)| y - beta * (x - control.mean))
+                .collect()
+        }
+        None => y,
+    };
+    let (estimate, variance) = mean_and_variance(&adjusted)?;
+    let standard_error = (variance / adjusted.len() as f64).sqrt();
+    let factor = if standard_error > 0.0 {
+        (plain_standard_error / standard_error).powi(2)
+    } else {
+        f64::INFINITY
+    };
+    Some(VarianceReduction {
+        antithetic,
+        control_variate: control.is_some(),
+        estimate,
+        standard_error,
+        plain_standard_error,
+        factor,
+    })
+}
+
+fn mean_and_variance(values: &[f64]) -> Option<(f64, f64)> {
+    if values.len() < 2 {
+        return None;
+    }
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    Some((mean, variance))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray_rand::rand::{Rng, SeedableRng};
+    use ndarray_rand::rand_distr::StandardNormal;
+    use rand_chacha::ChaCha12Rng;
+
+    #[test]
+    fn control_variate_agrees_with_the_plain_mean_and_reduces_its_variance() {
+        // Commissions of 2x plus noise, so their mean is 2 E[x] = 20 and the control x explains
+        // most of their variance.
+        let mut rng = ChaCha12Rng::seed_from_u64(7);
+        let controls: Vec<f64> = (0..10_000)
+            .map(|_| 10.0 + rng.sample::<f64, _>(StandardNormal))
+            .collect();
+        let commissions: Vec<f64> = controls
+            .iter()
+            .map(|x| 2.0 * x + 0.5 * rng.sample::<f64, _>(StandardNormal))
+            .collect();
+        let control = ControlVariate {
+            per_conversion: 1.0,
+            mean: 10.0,
+        };
+        let plain = estimate(&commissions, None, false).unwrap();
+        let reduced = estimate(&commissions, Some((&control, &controls)), false).unwrap();
+        assert!((reduced.estimate - plain.estimate).abs() <= 2.0 * plain.standard_error);
+        assert!((reduced.estimate - 20.0).abs() <= 2.0 * reduced.standard_error);
+        assert!(reduced.factor >= 1.0, "factor {}", reduced.factor);
+        assert_eq!(plain.plain_standard_error, reduced.plain_standard_error);
+    }
+
+    #[test]
+    fn antithetic_pairs_are_averaged_before_estimating() {
+        let commissions = [1.0, 3.0, 2.0, 2.0, 4.0, 0.0, 5.0];
+        let reduced = estimate(&commissions, None, true).unwrap();
+        // The unpaired last trial is left out and every pair averages to 2.
+        assert_eq!(reduced.estimate, 2.0);
+        assert_eq!(reduced.standard_error, 0.0);
+        assert_eq!(reduced.factor, f64::INFINITY);
+    }
+}